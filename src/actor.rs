@@ -0,0 +1,130 @@
+use crate::error::Error;
+use crate::scale::{Scale, Weight};
+use crate::source::RawSource;
+use phidget::devices::VoltageRatioInput;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+enum Command {
+    GetWeight(Sender<Result<Weight, Error>>),
+    Tare {
+        samples: usize,
+        timeout: Duration,
+        reply: Sender<Result<(), Error>>,
+    },
+    Calibrate {
+        empty_reading: f64,
+        weight_reading: f64,
+        weight: f64,
+        reply: Sender<Result<(), Error>>,
+    },
+}
+/// Talks to a [`Scale`] owned by its own thread, so the hardware handle never leaves it.
+pub struct ScaleHandle<S = VoltageRatioInput> {
+    commands: Sender<Command>,
+    thread: JoinHandle<Scale<S>>,
+}
+impl<S: RawSource + Send + 'static> Scale<S> {
+    pub fn spawn(self) -> ScaleHandle<S> {
+        let (commands, inbox) = mpsc::channel();
+        let thread = std::thread::spawn(move || run(self, inbox));
+        ScaleHandle { commands, thread }
+    }
+}
+fn run<S: RawSource>(mut scale: Scale<S>, inbox: Receiver<Command>) -> Scale<S> {
+    for command in inbox {
+        // A send only fails if the caller stopped waiting for the reply.
+        match command {
+            Command::GetWeight(reply) => {
+                let _ = reply.send(scale.get_weight());
+            }
+            Command::Tare {
+                samples,
+                timeout,
+                reply,
+            } => {
+                let _ = reply.send(scale.tare_averaged(samples, timeout));
+            }
+            Command::Calibrate {
+                empty_reading,
+                weight_reading,
+                weight,
+                reply,
+            } => {
+                let _ = reply.send(scale.set_calibration(empty_reading, weight_reading, weight));
+            }
+        }
+    }
+    scale
+}
+impl<S> ScaleHandle<S> {
+    fn request<T>(&self, command: impl FnOnce(Sender<T>) -> Command) -> Result<T, Error> {
+        let (reply, response) = mpsc::channel();
+        self.commands
+            .send(command(reply))
+            .map_err(|_| Error::ScaleStopped)?;
+        response.recv().map_err(|_| Error::ScaleStopped)
+    }
+    pub fn get_weight(&self) -> Result<Weight, Error> {
+        self.request(Command::GetWeight)?
+    }
+    pub fn tare(&self, samples: usize, timeout: Duration) -> Result<(), Error> {
+        self.request(|reply| Command::Tare {
+            samples,
+            timeout,
+            reply,
+        })?
+    }
+    pub fn calibrate(
+        &self,
+        empty_reading: f64,
+        weight_reading: f64,
+        weight: f64,
+    ) -> Result<(), Error> {
+        self.request(|reply| Command::Calibrate {
+            empty_reading,
+            weight_reading,
+            weight,
+            reply,
+        })?
+    }
+    /// Stops the scale thread once queued commands finish and hands the scale back.
+    pub fn shutdown(self) -> Result<Scale<S>, Error> {
+        drop(self.commands);
+        self.thread.join().map_err(|_| Error::ScaleStopped)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::DisconnectedScale;
+    use crate::source::ScriptedSource;
+    use menu::device::{Device, Model};
+    use menu::libra::Config;
+    #[test]
+    fn commands_round_trip_through_thread() -> Result<(), Error> {
+        let config = Config {
+            gain: 1.,
+            offset: 0.,
+            buffer_length: 3,
+            max_noise: 1.,
+            phidget_sample_period: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let handle = DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .connect_with_source(ScriptedSource::new([5.]))?
+            .spawn();
+        assert_eq!(handle.get_weight()?.get_amount(), 5.);
+        handle.tare(3, Duration::from_secs(1))?;
+        assert_eq!(handle.get_weight()?.get_amount(), 0.);
+        assert!(matches!(
+            handle.calibrate(2e-4, 1e-4, 1000.),
+            Err(Error::InvalidConfig(_))
+        ));
+        handle.calibrate(1e-4, 2e-4, 1000.)?;
+        let scale = handle.shutdown()?;
+        assert!((scale.get_config().gain - 1e7).abs() < 1.);
+        Ok(())
+    }
+}
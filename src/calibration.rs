@@ -0,0 +1,211 @@
+use crate::error::Error;
+use menu::device::Device;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    pub device: Device,
+    pub gain: f64,
+    pub offset: f64,
+    /// When the report was exported.
+    pub timestamp: SystemTime,
+    /// When the calibration itself was made; restore `Settings::calibrated_at` from this.
+    #[serde(default)]
+    pub calibrated_at: Option<SystemTime>,
+    pub verified_with: Option<f64>,
+}
+impl CalibrationReport {
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+    pub fn to_file(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub gain: f64,
+    pub offset: f64,
+}
+impl Calibration {
+    pub fn apply(&self, raw: f64) -> f64 {
+        raw * self.gain - self.offset
+    }
+}
+/// Converts captured raw readings with a calibration, for reprocessing old logs offline.
+pub fn convert_raw(samples: &[f64], gain: f64, offset: f64) -> Vec<f64> {
+    let calibration = Calibration { gain, offset };
+    samples.iter().map(|&raw| calibration.apply(raw)).collect()
+}
+pub fn convert_raw_in_place(samples: &mut [f64], gain: f64, offset: f64) {
+    let calibration = Calibration { gain, offset };
+    for sample in samples {
+        *sample = calibration.apply(*sample);
+    }
+}
+/// Calibrations taken at two temperatures, as `(celsius, calibration)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TempCalibration {
+    pub cold: (f64, Calibration),
+    pub hot: (f64, Calibration),
+}
+impl TempCalibration {
+    /// Linearly interpolates gain and offset, holding the nearer end outside the range.
+    pub fn at(&self, temperature: f64) -> Calibration {
+        let ((cold_t, cold), (hot_t, hot)) = (self.cold, self.hot);
+        if hot_t == cold_t {
+            return cold;
+        }
+        let t = ((temperature - cold_t) / (hot_t - cold_t)).clamp(0., 1.);
+        Calibration {
+            gain: cold.gain + t * (hot.gain - cold.gain),
+            offset: cold.offset + t * (hot.offset - cold.offset),
+        }
+    }
+}
+#[derive(Serialize, Deserialize)]
+struct StoredCalibration {
+    phidget_id: i32,
+    load_cell_id: i32,
+    #[serde(flatten)]
+    calibration: Calibration,
+}
+/// Calibrations keyed by `(phidget_id, load_cell_id)`, independent of any `Libra` config.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalibrationStore {
+    entries: HashMap<(i32, i32), Calibration>,
+}
+impl CalibrationStore {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let stored: Vec<StoredCalibration> = serde_json::from_str(json)?;
+        Ok(Self {
+            entries: stored
+                .into_iter()
+                .map(|s| ((s.phidget_id, s.load_cell_id), s.calibration))
+                .collect(),
+        })
+    }
+    pub fn to_json(&self) -> Result<String, Error> {
+        let mut stored: Vec<StoredCalibration> = self
+            .entries
+            .iter()
+            .map(
+                |(&(phidget_id, load_cell_id), &calibration)| StoredCalibration {
+                    phidget_id,
+                    load_cell_id,
+                    calibration,
+                },
+            )
+            .collect();
+        stored.sort_by_key(|s| (s.phidget_id, s.load_cell_id));
+        Ok(serde_json::to_string_pretty(&stored)?)
+    }
+    pub fn get(&self, phidget_id: i32, load_cell_id: i32) -> Option<Calibration> {
+        self.entries.get(&(phidget_id, load_cell_id)).copied()
+    }
+    pub fn set(&mut self, phidget_id: i32, load_cell_id: i32, calibration: Calibration) {
+        self.entries.insert((phidget_id, load_cell_id), calibration);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use menu::device::Model;
+    #[test]
+    fn serde_round_trip() -> Result<(), Error> {
+        let report = CalibrationReport {
+            device: Device::new(Model::LibraV0, "L0"),
+            gain: 10_000_000.,
+            offset: 31.4,
+            timestamp: SystemTime::now(),
+            calibrated_at: Some(SystemTime::UNIX_EPOCH),
+            verified_with: Some(1277.),
+        };
+        let parsed: CalibrationReport = serde_json::from_str(&report.to_json()?)?;
+        assert_eq!(parsed.device.to_string(), report.device.to_string());
+        assert_eq!(parsed.gain, report.gain);
+        assert_eq!(parsed.offset, report.offset);
+        assert_eq!(parsed.timestamp, report.timestamp);
+        assert_eq!(parsed.calibrated_at, report.calibrated_at);
+        assert_eq!(parsed.verified_with, report.verified_with);
+        Ok(())
+    }
+    #[test]
+    fn temp_calibration_interpolates_and_clamps() {
+        let temp = TempCalibration {
+            cold: (
+                0.,
+                Calibration {
+                    gain: 4_000_000.,
+                    offset: 10.,
+                },
+            ),
+            hot: (
+                40.,
+                Calibration {
+                    gain: 4_200_000.,
+                    offset: 30.,
+                },
+            ),
+        };
+        let mid = temp.at(20.);
+        assert!((mid.gain - 4_100_000.).abs() < 1e-6);
+        assert!((mid.offset - 20.).abs() < 1e-9);
+        assert!((mid.apply(1e-3) - 4_080.).abs() < 1e-6);
+        assert_eq!(temp.at(-15.), temp.cold.1);
+        assert_eq!(temp.at(60.), temp.hot.1);
+    }
+    #[test]
+    fn store_round_trip() -> Result<(), Error> {
+        let mut store = CalibrationStore::default();
+        store.set(
+            716588,
+            0,
+            Calibration {
+                gain: 4_500_000.,
+                offset: 12.5,
+            },
+        );
+        store.set(
+            716588,
+            1,
+            Calibration {
+                gain: -3_900_000.,
+                offset: -8.,
+            },
+        );
+        let path = std::env::temp_dir().join(format!("calibrations-{}.json", std::process::id()));
+        store.save(&path)?;
+        let loaded = CalibrationStore::load(&path);
+        std::fs::remove_file(&path)?;
+        assert_eq!(loaded?, store);
+        Ok(())
+    }
+    #[test]
+    fn store_lookup() -> Result<(), Error> {
+        let store = CalibrationStore::from_json(
+            r#"[{"phidget_id": 716588, "load_cell_id": 1, "gain": 2.0, "offset": 0.5}]"#,
+        )?;
+        assert_eq!(
+            store.get(716588, 1),
+            Some(Calibration {
+                gain: 2.,
+                offset: 0.5
+            })
+        );
+        assert_eq!(store.get(716588, 0), None);
+        assert_eq!(store.get(716589, 1), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use crate::error::Error;
+use crate::math;
+use crate::scale::Scale;
+use crate::source::RawSource;
+use log::{info, warn};
+use std::time::Instant;
+
+pub struct DriftMonitor {
+    zero_readings: Vec<(Instant, f64)>,
+    capacity: usize,
+    max_drift_per_hour: f64,
+    #[cfg(feature = "temperature")]
+    thermal_readings: Vec<(f64, f64)>,
+}
+impl DriftMonitor {
+    /// A zero `capacity` keeps no history, so the monitor never reports drift.
+    pub fn new(capacity: usize, max_drift_per_hour: f64) -> Self {
+        Self {
+            zero_readings: Vec::with_capacity(capacity),
+            capacity,
+            max_drift_per_hour,
+            #[cfg(feature = "temperature")]
+            thermal_readings: Vec::with_capacity(capacity),
+        }
+    }
+    pub fn sample<S: RawSource>(&mut self, scale: &Scale<S>) -> Result<(), Error> {
+        let zero = scale.get_instant_weight()?;
+        self.record(Instant::now(), zero);
+        #[cfg(feature = "temperature")]
+        match scale.temperature() {
+            Ok(temperature) => self.record_temperature(temperature, zero),
+            Err(Error::NoTemperature) => {}
+            Err(e) => return Err(e),
+        }
+        Ok(())
+    }
+    pub fn record(&mut self, at: Instant, zero: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.zero_readings.len() == self.capacity {
+            self.zero_readings.remove(0);
+        }
+        self.zero_readings.push((at, zero));
+        let rate = self.drift_rate();
+        if self.is_drifting() {
+            warn!(
+                "Zero drifting at {rate:.3} g/h (limit {})",
+                self.max_drift_per_hour
+            );
+        } else {
+            info!("Zero drift: {rate:.3} g/h");
+        }
+    }
+    pub fn drift_rate(&self) -> f64 {
+        let Some(&(start, _)) = self.zero_readings.first() else {
+            return 0.;
+        };
+        let points: Vec<(f64, f64)> = self
+            .zero_readings
+            .iter()
+            .map(|(at, zero)| (at.duration_since(start).as_secs_f64() / 3600., *zero))
+            .collect();
+        math::slope_xy(&points)
+    }
+    #[cfg(feature = "temperature")]
+    pub fn record_temperature(&mut self, temperature: f64, zero: f64) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.thermal_readings.len() == self.capacity {
+            self.thermal_readings.remove(0);
+        }
+        self.thermal_readings.push((temperature, zero));
+        info!(
+            "Zero {zero:.3} g at {temperature:.2} C ({:.3} g/C)",
+            self.thermal_coefficient()
+        );
+    }
+    /// Zero shift per degree, for compensating readings taken at other temperatures.
+    #[cfg(feature = "temperature")]
+    pub fn thermal_coefficient(&self) -> f64 {
+        math::slope_xy(&self.thermal_readings)
+    }
+    /// Hands the measured coefficient to `scale`, referenced to its current temperature.
+    #[cfg(feature = "temperature")]
+    pub fn compensate<S: RawSource>(&self, scale: &mut Scale<S>) -> Result<(), Error> {
+        scale.set_thermal_coefficient(Some(self.thermal_coefficient()))
+    }
+    pub fn is_drifting(&self) -> bool {
+        self.drift_rate().abs() > self.max_drift_per_hour
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    #[test]
+    fn drift_rate_matches_known_slope() {
+        let mut monitor = DriftMonitor::new(10, 1.);
+        let start = Instant::now();
+        for i in 0..5 {
+            let at = start + Duration::from_secs(1800 * i);
+            monitor.record(at, 0.2 + 2. * (i as f64 * 0.5));
+        }
+        assert!((monitor.drift_rate() - 2.).abs() < 1e-9);
+        assert!(monitor.is_drifting());
+    }
+    #[cfg(feature = "temperature")]
+    #[test]
+    fn thermal_coefficient_from_temperature_samples() {
+        let mut monitor = DriftMonitor::new(10, 1.);
+        for (temperature, zero) in [(20., 0.1), (25., 0.35), (30., 0.6)] {
+            monitor.record_temperature(temperature, zero);
+        }
+        assert!((monitor.thermal_coefficient() - 0.05).abs() < 1e-9);
+    }
+    #[test]
+    fn drift_rate_needs_two_readings() {
+        let mut monitor = DriftMonitor::new(10, 1.);
+        assert_eq!(monitor.drift_rate(), 0.);
+        monitor.record(Instant::now(), 3.);
+        assert_eq!(monitor.drift_rate(), 0.);
+        assert!(!monitor.is_drifting());
+    }
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut monitor = DriftMonitor::new(0, 1.);
+        monitor.record(Instant::now(), 3.);
+        monitor.record(Instant::now(), 30.);
+        assert_eq!(monitor.drift_rate(), 0.);
+        #[cfg(feature = "temperature")]
+        monitor.record_temperature(20., 0.1);
+    }
+}
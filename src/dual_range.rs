@@ -0,0 +1,98 @@
+use crate::error::Error;
+use crate::scale::{Scale, Weight};
+use crate::source::RawSource;
+use phidget::devices::VoltageRatioInput;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Range {
+    Fine,
+    Coarse,
+}
+/// A sensitive cell and a coarse cell under one pan, each with its own calibration. The
+/// coarse reading picks the range: it hands off to the coarse cell above
+/// `crossover + hysteresis` and back to the fine cell below `crossover - hysteresis`, so a
+/// load resting near the crossover doesn't flicker between them.
+pub struct DualRangeScale<S = VoltageRatioInput> {
+    fine: Scale<S>,
+    coarse: Scale<S>,
+    crossover: f64,
+    hysteresis: f64,
+    range: Range,
+}
+impl<S: RawSource> DualRangeScale<S> {
+    pub fn new(fine: Scale<S>, coarse: Scale<S>, crossover: f64, hysteresis: f64) -> Self {
+        Self {
+            fine,
+            coarse,
+            crossover,
+            hysteresis: hysteresis.abs(),
+            range: Range::Fine,
+        }
+    }
+    /// Reads both cells, keeping both buffers current, and returns the active cell's weight.
+    pub fn get_weight(&mut self) -> Result<Weight, Error> {
+        let fine = self.fine.get_weight()?;
+        let coarse = self.coarse.get_weight()?;
+        let load = coarse.get_amount();
+        self.range = match self.range {
+            Range::Fine if load > self.crossover + self.hysteresis => Range::Coarse,
+            Range::Coarse if load < self.crossover - self.hysteresis => Range::Fine,
+            range => range,
+        };
+        Ok(match self.range {
+            Range::Fine => fine,
+            Range::Coarse => coarse,
+        })
+    }
+    pub fn get_range(&self) -> Range {
+        self.range
+    }
+    pub fn get_fine(&mut self) -> &mut Scale<S> {
+        &mut self.fine
+    }
+    pub fn get_coarse(&mut self) -> &mut Scale<S> {
+        &mut self.coarse
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::DisconnectedScale;
+    use crate::source::ScriptedSource;
+    use menu::device::{Device, Model};
+    use menu::libra::Config;
+    fn channel(name: &str, gain: f64, readings: [f64; 6]) -> Scale<ScriptedSource> {
+        let config = Config {
+            gain,
+            offset: 0.,
+            buffer_length: 1,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        DisconnectedScale::new(config, Device::new(Model::LibraV0, name))
+            .connect_with_source(ScriptedSource::new(readings))
+            .unwrap()
+    }
+    #[test]
+    fn hands_off_with_hysteresis() -> Result<(), Error> {
+        let fine = channel("L0", 1., [50., 103., 110., 98., 103., 90.]);
+        let coarse = channel("L1", 0.5, [102., 207., 221., 196., 206., 181.]);
+        let mut scale = DualRangeScale::new(fine, coarse, 100., 5.);
+        let mut readings = Vec::new();
+        for _ in 0..6 {
+            readings.push((scale.get_weight()?.get_amount(), scale.get_range()));
+        }
+        assert_eq!(
+            readings,
+            vec![
+                (50., Range::Fine),
+                (103., Range::Fine),
+                (110.5, Range::Coarse),
+                (98., Range::Coarse),
+                (103., Range::Coarse),
+                (90., Range::Fine),
+            ]
+        );
+        Ok(())
+    }
+}
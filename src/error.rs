@@ -12,9 +12,52 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[cfg(feature = "find_phidgets")]
     #[error("USB Error: {0}")]
-    Rusb(#[from] rusb::Error),
+    Rusb(rusb::Error),
+    #[cfg(feature = "find_phidgets")]
+    #[error(
+        "Permission denied opening USB device: install the phidget udev rules or run as a user in the plugdev group"
+    )]
+    UsbPermission,
     #[error("Couldn't Cast String to Int")]
     ParseInt,
     #[error("Timed out")]
     Timeout,
+    #[error("Value out of supported range")]
+    OutOfRange,
+    #[error("Invalid Config: {0}")]
+    InvalidConfig(String),
+    #[error("Scale thread stopped")]
+    ScaleStopped,
+    #[error("Scale is paused")]
+    Paused,
+    #[cfg(feature = "temperature")]
+    #[error("No temperature channel attached")]
+    NoTemperature,
+}
+
+#[cfg(feature = "find_phidgets")]
+impl From<rusb::Error> for Error {
+    fn from(e: rusb::Error) -> Self {
+        match e {
+            rusb::Error::Access => Error::UsbPermission,
+            e => Error::Rusb(e),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "find_phidgets"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_error_maps_to_usb_permission() {
+        assert!(matches!(
+            Error::from(rusb::Error::Access),
+            Error::UsbPermission
+        ));
+        assert!(matches!(
+            Error::from(rusb::Error::Busy),
+            Error::Rusb(rusb::Error::Busy)
+        ));
+    }
 }
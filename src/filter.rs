@@ -0,0 +1,148 @@
+use crate::math;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+/// Smoothing applied to each reading before it reaches the weight buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub enum Filter {
+    #[default]
+    None,
+    /// Mean of the latest `n` readings.
+    Mean(usize),
+    /// Median of the latest `n` readings.
+    Median(usize),
+    /// Exponential moving average; the value is the weight of the new reading.
+    Ema(f64),
+    /// Replaces a reading with the window median when it's more than `k` scaled MADs away.
+    Hampel { window: usize, k: f64 },
+    /// First-order low-pass with a `cutoff` in Hz, derived from the sample period.
+    LowPass { cutoff: f64 },
+}
+impl Filter {
+    fn window(&self) -> usize {
+        match *self {
+            Filter::Mean(n) | Filter::Median(n) => n.max(1),
+            Filter::Hampel { window, .. } => window.max(1),
+            _ => 0,
+        }
+    }
+}
+/// A [`Filter`] and the readings it has seen.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FilterState {
+    filter: Filter,
+    history: VecDeque<f64>,
+    smoothed: Option<f64>,
+}
+impl FilterState {
+    pub(crate) fn new(filter: Filter) -> Self {
+        Self {
+            filter,
+            ..Default::default()
+        }
+    }
+    pub(crate) fn filter(&self) -> Filter {
+        self.filter
+    }
+    pub(crate) fn reset(&mut self) {
+        self.history.clear();
+        self.smoothed = None;
+    }
+    pub(crate) fn apply(&mut self, reading: f64, sample_period: Duration) -> f64 {
+        let window = self.filter.window();
+        if window > 0 {
+            if self.history.len() == window {
+                self.history.pop_front();
+            }
+            self.history.push_back(reading);
+        }
+        match self.filter {
+            Filter::None => reading,
+            Filter::Mean(_) => math::mean(self.history.make_contiguous()).unwrap_or(reading),
+            Filter::Median(_) => {
+                let mut window: Vec<f64> = self.history.iter().copied().collect();
+                math::median(&mut window).unwrap_or(reading)
+            }
+            Filter::Hampel { k, .. } => {
+                let mut window: Vec<f64> = self.history.iter().copied().collect();
+                let median = math::median(&mut window).unwrap_or(reading);
+                let mut deviations: Vec<f64> = window.iter().map(|w| (w - median).abs()).collect();
+                let mad = math::median(&mut deviations).unwrap_or(0.);
+                // 1.4826 scales the MAD to a standard deviation for normal noise.
+                if (reading - median).abs() > k * 1.4826 * mad {
+                    median
+                } else {
+                    reading
+                }
+            }
+            Filter::Ema(alpha) => self.smooth(reading, alpha),
+            Filter::LowPass { cutoff } if cutoff > 0. => {
+                let dt = sample_period.as_secs_f64();
+                let rc = 1. / (2. * PI * cutoff);
+                self.smooth(reading, dt / (rc + dt))
+            }
+            Filter::LowPass { .. } => reading,
+        }
+    }
+    fn smooth(&mut self, reading: f64, alpha: f64) -> f64 {
+        let smoothed = match self.smoothed {
+            Some(previous) => math::ema(previous, reading, alpha),
+            None => reading,
+        };
+        self.smoothed = Some(smoothed);
+        smoothed
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn run(filter: Filter, readings: &[f64]) -> Vec<f64> {
+        let mut state = FilterState::new(filter);
+        readings
+            .iter()
+            .map(|&r| state.apply(r, Duration::from_millis(50)))
+            .collect()
+    }
+    #[test]
+    fn none_passes_through() {
+        assert_eq!(run(Filter::None, &[1., 5., 3.]), vec![1., 5., 3.]);
+    }
+    #[test]
+    fn mean_and_median_over_window() {
+        assert_eq!(run(Filter::Mean(2), &[1., 5., 3.]), vec![1., 3., 4.]);
+        assert_eq!(
+            run(Filter::Median(3), &[1., 50., 3., 4.]),
+            vec![1., 25.5, 3., 4.]
+        );
+    }
+    #[test]
+    fn ema_seeds_then_smooths() {
+        assert_eq!(
+            run(Filter::Ema(0.5), &[10., 20., 20.]),
+            vec![10., 15., 17.5]
+        );
+    }
+    #[test]
+    fn hampel_replaces_only_outliers() {
+        let out = run(
+            Filter::Hampel { window: 5, k: 3. },
+            &[10., 10.2, 9.9, 10.1, 80., 10.],
+        );
+        assert_eq!(&out[..4], &[10., 10.2, 9.9, 10.1]);
+        assert_eq!(out[4], 10.1);
+        assert_eq!(out[5], 10.);
+    }
+    #[test]
+    fn low_pass_uses_sample_period() {
+        // 50 ms samples at a 1/(2π·0.05) Hz cutoff give alpha = 0.05 / (1 + 0.05).
+        let cutoff = 1. / (2. * PI);
+        let out = run(Filter::LowPass { cutoff }, &[0., 21.]);
+        assert!((out[1] - 1.).abs() < 1e-9);
+        assert_eq!(
+            run(Filter::LowPass { cutoff: 0. }, &[0., 21.]),
+            vec![0., 21.]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::error::Error;
+use crate::math;
+use crate::scale::{Scale, Weight};
+use crate::source::RawSource;
+use log::warn;
+use menu::device::Device;
+use phidget::devices::VoltageRatioInput;
+use std::time::Duration;
+
+pub struct ScaleGroup<S = VoltageRatioInput> {
+    scales: Vec<Scale<S>>,
+    baselines: Vec<Option<f64>>,
+}
+impl<S: RawSource> ScaleGroup<S> {
+    pub fn new(scales: Vec<Scale<S>>) -> Self {
+        let baselines = vec![None; scales.len()];
+        Self { scales, baselines }
+    }
+    pub fn get_scales(&mut self) -> &mut [Scale<S>] {
+        &mut self.scales
+    }
+    pub fn get_devices(&self) -> Vec<Device> {
+        self.scales.iter().map(Scale::get_device).collect()
+    }
+    pub fn tare_all(
+        &mut self,
+        samples: usize,
+        timeout: Duration,
+    ) -> Vec<(Device, Result<(), Error>)> {
+        self.scales
+            .iter_mut()
+            .map(|scale| (scale.get_device(), scale.tare_averaged(samples, timeout)))
+            .collect()
+    }
+    /// Reads the scales in order and returns the index and delta of the first whose stable
+    /// weight moved more than `threshold` since its last stable level. Scales after it
+    /// aren't read, so a change on another pan in the same poll is reported on the next.
+    pub fn first_changed(&mut self, threshold: f64) -> Option<(usize, f64)> {
+        for (i, (scale, baseline)) in self
+            .scales
+            .iter_mut()
+            .zip(self.baselines.iter_mut())
+            .enumerate()
+        {
+            let weight = match scale.get_weight() {
+                Ok(weight) if weight.is_stable() => weight.get_amount(),
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Failed to read scale {}: {e}", scale.get_device());
+                    continue;
+                }
+            };
+            match *baseline {
+                Some(level) if (weight - level).abs() > threshold => {
+                    *baseline = Some(weight);
+                    return Some((i, weight - level));
+                }
+                Some(_) => {}
+                None => *baseline = Some(weight),
+            }
+        }
+        None
+    }
+    /// Reads every scale as a redundant measurement of one load and returns the mean of
+    /// the readings that agree with the median, warning about any cell that doesn't.
+    pub fn consensus_weight(&mut self) -> Result<Consensus, Error> {
+        let mut readings = Vec::with_capacity(self.scales.len());
+        let mut excluded = Vec::new();
+        let mut first_error = None;
+        for scale in &mut self.scales {
+            match scale.get_weight() {
+                Ok(weight) => readings.push((
+                    scale.get_device(),
+                    weight.get_amount(),
+                    scale.get_settings().weight_unit,
+                )),
+                Err(e) => {
+                    warn!("Excluding scale {} from consensus: {e}", scale.get_device());
+                    excluded.push(scale.get_device());
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        if readings.is_empty() {
+            return Err(
+                first_error.unwrap_or_else(|| Error::InvalidConfig("group has no scales".into()))
+            );
+        }
+        let mut values: Vec<f64> = readings.iter().map(|(_, w, _)| *w).collect();
+        let center = math::median(&mut values).unwrap_or_default();
+        let mut deviations: Vec<f64> = readings
+            .iter()
+            .map(|(_, w, _)| (w - center).abs())
+            .collect();
+        let spread = math::median(&mut deviations).unwrap_or_default();
+        let noise = self
+            .scales
+            .iter()
+            .map(|scale| scale.get_config().max_noise)
+            .fold(0., f64::max);
+        let limit = (spread * MAD_OUTLIER_SCALE).max(noise);
+        let (agreeing, outliers): (Vec<_>, Vec<_>) = readings
+            .into_iter()
+            .partition(|(_, w, _)| (w - center).abs() <= limit);
+        for (device, weight, unit) in outliers {
+            warn!("Scale {device} disagrees with consensus {center:.2} {unit}: {weight:.2} {unit}");
+            excluded.push(device);
+        }
+        Ok(Consensus {
+            weight: agreeing.iter().map(|(_, w, _)| w).sum::<f64>() / agreeing.len() as f64,
+            excluded,
+        })
+    }
+    /// Like reading each scale in turn, but with one thread per scale so the USB reads overlap.
+    pub fn read_all_parallel(&mut self) -> Vec<(Device, Result<Weight, Error>)>
+    where
+        S: Send,
+    {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .scales
+                .iter_mut()
+                .map(|scale| scope.spawn(|| (scale.get_device(), scale.get_weight())))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("scale read panicked"))
+                .collect()
+        })
+    }
+}
+#[derive(Debug, Clone)]
+pub struct Consensus {
+    pub weight: f64,
+    /// Scales that failed to read, followed by those that disagreed with the rest.
+    pub excluded: Vec<Device>,
+}
+/// Readings further than this many median absolute deviations from the median are outliers.
+const MAD_OUTLIER_SCALE: f64 = 4.;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::DisconnectedScale;
+    use crate::source::ScriptedSource;
+    use menu::device::Model;
+    use menu::libra::Config;
+    fn scripted(name: &str, source: ScriptedSource) -> Scale<ScriptedSource> {
+        let config = Config {
+            gain: 1.,
+            offset: 0.,
+            buffer_length: 3,
+            max_noise: 1.,
+            phidget_sample_period: Duration::from_millis(1),
+            ..Default::default()
+        };
+        DisconnectedScale::new(config, Device::new(Model::LibraV0, name))
+            .connect_with_source(source)
+            .unwrap()
+    }
+    #[test]
+    fn tare_all_collects_per_scale_results() {
+        let mut group = ScaleGroup::new(vec![
+            scripted("L0", ScriptedSource::new([12.])),
+            scripted("L1", ScriptedSource::cycling([0., 40., -40.])),
+            scripted("L2", ScriptedSource::new([-3.])),
+        ]);
+        let results = group.tare_all(3, Duration::from_millis(20));
+        let names: Vec<String> = results.iter().map(|(d, _)| d.to_string()).collect();
+        assert_eq!(
+            names,
+            group
+                .get_devices()
+                .iter()
+                .map(Device::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert!(results[0].1.is_ok());
+        assert!(matches!(results[1].1, Err(Error::Timeout)));
+        assert!(results[2].1.is_ok());
+        let scales = group.get_scales();
+        assert_eq!(scales[0].get_config().offset, 12.);
+        assert_eq!(scales[2].get_config().offset, -3.);
+        assert_eq!(scales[1].get_config().offset, 0.);
+    }
+    #[test]
+    fn first_changed_routes_to_the_loaded_channel() {
+        let mut group = ScaleGroup::new(vec![
+            scripted("L0", ScriptedSource::new([5.])),
+            scripted("L1", ScriptedSource::new([0., 0., 0., 0., 50.])),
+            scripted("L2", ScriptedSource::new([-3.])),
+        ]);
+        let changes: Vec<_> = (0..8).filter_map(|_| group.first_changed(10.)).collect();
+        assert_eq!(changes, vec![(1, 50.)]);
+    }
+    #[test]
+    fn first_changed_keeps_simultaneous_changes() {
+        let mut group = ScaleGroup::new(vec![
+            scripted("L0", ScriptedSource::new([0., 0., 0., 0., 20.])),
+            scripted("L1", ScriptedSource::new([0., 0., 0., 0., 50.])),
+        ]);
+        let changes: Vec<_> = (0..8).filter_map(|_| group.first_changed(10.)).collect();
+        assert_eq!(changes, vec![(0, 20.), (1, 50.)]);
+    }
+    #[test]
+    fn consensus_excludes_a_bad_cell() -> Result<(), Error> {
+        let mut group = ScaleGroup::new(vec![
+            scripted("L0", ScriptedSource::new([100.])),
+            scripted("L1", ScriptedSource::new([101.])),
+            scripted("L2", ScriptedSource::new([500.])),
+            scripted("L3", ScriptedSource::new([99.])),
+        ]);
+        let consensus = group.consensus_weight()?;
+        assert_eq!(consensus.weight, 100.);
+        assert_eq!(
+            consensus
+                .excluded
+                .iter()
+                .map(Device::to_string)
+                .collect::<Vec<_>>(),
+            vec![Device::new(Model::LibraV0, "L2").to_string()]
+        );
+        Ok(())
+    }
+    #[test]
+    fn consensus_needs_a_reading() {
+        let mut group = ScaleGroup::new(vec![scripted("L0", ScriptedSource::new([]))]);
+        assert!(matches!(group.consensus_weight(), Err(Error::Timeout)));
+        let mut empty: ScaleGroup<ScriptedSource> = ScaleGroup::new(Vec::new());
+        assert!(matches!(
+            empty.consensus_weight(),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+    #[test]
+    fn read_all_parallel_isolates_errors() {
+        let mut group = ScaleGroup::new(vec![
+            scripted("L0", ScriptedSource::new([12.])),
+            scripted("L1", ScriptedSource::new([])),
+            scripted("L2", ScriptedSource::new([-3.])),
+        ]);
+        let results = group.read_all_parallel();
+        let names: Vec<String> = results.iter().map(|(d, _)| d.to_string()).collect();
+        assert_eq!(
+            names,
+            group
+                .get_devices()
+                .iter()
+                .map(Device::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(results[0].1.as_ref().unwrap().get_amount(), 12.);
+        assert!(matches!(results[1].1, Err(Error::Timeout)));
+        assert_eq!(results[2].1.as_ref().unwrap().get_amount(), -3.);
+    }
+}
@@ -1,2 +1,15 @@
+pub mod actor;
+pub mod calibration;
+pub mod drift;
+pub mod dual_range;
 pub mod error;
+pub mod filter;
+pub mod group;
+pub mod manager;
+pub mod math;
+pub mod presence;
+pub mod recorder;
 pub mod scale;
+pub mod settings;
+pub mod source;
+pub mod unit;
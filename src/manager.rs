@@ -0,0 +1,214 @@
+use crate::error::Error;
+use crate::scale::{DisconnectedScale, Scale};
+use log::warn;
+use menu::device::Device;
+#[cfg(feature = "find_phidgets")]
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum HotplugEvent {
+    Connected(Device),
+    Disconnected(Device),
+}
+pub struct ScaleManager {
+    scales: Vec<Scale>,
+    missing: Vec<DisconnectedScale>,
+    connector: fn(DisconnectedScale) -> Result<Scale, Error>,
+}
+impl ScaleManager {
+    #[cfg(feature = "find_phidgets")]
+    pub fn auto_connect(path: &Path) -> Result<Self, Error> {
+        let connected_ids = DisconnectedScale::get_connected_phidget_ids()?;
+        Self::connect_present(DisconnectedScale::from_config(path)?, &connected_ids)
+    }
+    /// Scales that are discovered but fail to connect join the missing ones, to be retried
+    /// by [`ScaleManager::apply_discovery`].
+    pub fn connect_present(
+        configured: Vec<DisconnectedScale>,
+        connected_ids: &[isize],
+    ) -> Result<Self, Error> {
+        Ok(Self::connect_present_with(
+            configured,
+            connected_ids,
+            DisconnectedScale::connect,
+        ))
+    }
+    fn connect_present_with(
+        configured: Vec<DisconnectedScale>,
+        connected_ids: &[isize],
+        connector: fn(DisconnectedScale) -> Result<Scale, Error>,
+    ) -> Self {
+        let (present, missing) = partition_present(configured, connected_ids);
+        for scale in &missing {
+            warn!("Configured scale {} is not connected", scale.get_device());
+        }
+        let mut manager = Self {
+            scales: Vec::new(),
+            missing,
+            connector,
+        };
+        for scale in present {
+            manager.connect_or_defer(scale);
+        }
+        manager
+    }
+    #[cfg(feature = "find_phidgets")]
+    pub fn poll_hotplug(&mut self) -> Vec<HotplugEvent> {
+        match DisconnectedScale::get_connected_phidget_ids() {
+            Ok(connected_ids) => self.apply_discovery(&connected_ids),
+            Err(e) => {
+                warn!("Phidget discovery failed: {e}");
+                Vec::new()
+            }
+        }
+    }
+    pub fn apply_discovery(&mut self, connected_ids: &[isize]) -> Vec<HotplugEvent> {
+        let mut events = Vec::new();
+        let (present, gone): (Vec<Scale>, Vec<Scale>) = self
+            .scales
+            .drain(..)
+            .partition(|scale| is_discovered(scale.get_config().phidget_id, connected_ids));
+        self.scales = present;
+        for scale in gone {
+            let device = scale.get_device();
+            let disconnected = DisconnectedScale::new(scale.get_config(), scale.get_device())
+                .with_settings(scale.get_settings());
+            if let Err(e) = scale.disconnect() {
+                warn!("Failed to close scale {device}: {e}");
+            }
+            self.missing.push(disconnected);
+            events.push(HotplugEvent::Disconnected(device));
+        }
+        let (appeared, missing) =
+            partition_present(std::mem::take(&mut self.missing), connected_ids);
+        self.missing = missing;
+        for scale in appeared {
+            let device = scale.get_device();
+            if self.connect_or_defer(scale) {
+                events.push(HotplugEvent::Connected(device));
+            }
+        }
+        events
+    }
+    fn connect_or_defer(&mut self, scale: DisconnectedScale) -> bool {
+        let device = scale.get_device();
+        let retry = scale.clone();
+        match (self.connector)(scale) {
+            Ok(scale) => {
+                self.scales.push(scale);
+                true
+            }
+            Err(e) => {
+                warn!("Failed to connect scale {device}: {e}");
+                self.missing.push(retry);
+                false
+            }
+        }
+    }
+    pub fn get_scales(&mut self) -> &mut [Scale] {
+        &mut self.scales
+    }
+    pub fn get_missing(&self) -> Vec<Device> {
+        self.missing
+            .iter()
+            .map(DisconnectedScale::get_device)
+            .collect()
+    }
+}
+pub(crate) fn is_discovered(phidget_id: i32, connected_ids: &[isize]) -> bool {
+    connected_ids.contains(&(phidget_id as isize))
+}
+fn partition_present(
+    configured: Vec<DisconnectedScale>,
+    connected_ids: &[isize],
+) -> (Vec<DisconnectedScale>, Vec<DisconnectedScale>) {
+    configured
+        .into_iter()
+        .partition(|scale| is_discovered(scale.get_config().phidget_id, connected_ids))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use menu::device::Model;
+    use menu::libra::Config;
+    fn configured(phidget_id: i32, name: &str) -> DisconnectedScale {
+        let config = Config {
+            phidget_id,
+            ..Default::default()
+        };
+        DisconnectedScale::new(config, Device::new(Model::LibraV0, name))
+    }
+    fn name(number: &str) -> String {
+        Device::new(Model::LibraV0, number).to_string()
+    }
+    #[test]
+    fn matches_discovered_ids_to_config() {
+        let configs = vec![
+            configured(716588, "L0"),
+            configured(716589, "L1"),
+            configured(716590, "L2"),
+        ];
+        let (present, missing) = partition_present(configs, &[716590, 716588, 1]);
+        let present: Vec<String> = present.iter().map(|s| s.get_device().to_string()).collect();
+        let missing: Vec<String> = missing.iter().map(|s| s.get_device().to_string()).collect();
+        assert_eq!(present, vec![name("L0"), name("L2")]);
+        assert_eq!(missing, vec![name("L1")]);
+    }
+    #[test]
+    fn nothing_discovered_reports_all_missing() -> Result<(), Error> {
+        let mut manager = ScaleManager::connect_present(vec![configured(716588, "L0")], &[])?;
+        assert!(manager.get_scales().is_empty());
+        assert_eq!(manager.get_missing().len(), 1);
+        Ok(())
+    }
+    #[test]
+    fn failed_connect_is_reported_missing() {
+        fn flaky(scale: DisconnectedScale) -> Result<Scale, Error> {
+            if scale.get_config().phidget_id == 716589 {
+                Err(Error::Timeout)
+            } else {
+                scale.connect_unopened()
+            }
+        }
+        let mut manager = ScaleManager::connect_present_with(
+            vec![configured(716588, "L0"), configured(716589, "L1")],
+            &[716588, 716589],
+            flaky,
+        );
+        assert_eq!(manager.get_scales().len(), 1);
+        assert_eq!(
+            manager
+                .get_missing()
+                .iter()
+                .map(Device::to_string)
+                .collect::<Vec<_>>(),
+            vec![name("L1")]
+        );
+    }
+    #[test]
+    fn hotplug_emits_connect_and_disconnect() {
+        let mut manager = ScaleManager {
+            scales: Vec::new(),
+            missing: vec![configured(716588, "L0"), configured(716589, "L1")],
+            connector: DisconnectedScale::connect_unopened,
+        };
+        let events = manager.apply_discovery(&[716588]);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], HotplugEvent::Connected(d) if d.to_string() == name("L0")));
+
+        let events = manager.apply_discovery(&[716589]);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], HotplugEvent::Disconnected(d) if d.to_string() == name("L0")));
+        assert!(matches!(&events[1], HotplugEvent::Connected(d) if d.to_string() == name("L1")));
+        assert_eq!(manager.get_scales().len(), 1);
+        assert_eq!(
+            manager
+                .get_missing()
+                .iter()
+                .map(Device::to_string)
+                .collect::<Vec<_>>(),
+            vec![name("L0")]
+        );
+        assert!(manager.apply_discovery(&[716589]).is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+//! Allocation-free signal math shared by [`crate::scale::Scale`]. Nothing here touches
+//! std or the phidget driver, so it can be reused on a microcontroller reading a bridge
+//! ADC directly.
+
+pub fn peak_to_peak(samples: &[f64]) -> f64 {
+    let max = samples.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+    let min = samples.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+    max - min
+}
+pub fn mean(samples: &[f64]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    Some(samples.iter().sum::<f64>() / samples.len() as f64)
+}
+/// Sorts `samples` in place.
+pub fn median(samples: &mut [f64]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable_by(f64::total_cmp);
+    let mid = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        Some((samples[mid - 1] + samples[mid]) / 2.)
+    } else {
+        Some(samples[mid])
+    }
+}
+/// Whether the latest `window` samples are within `max_noise` of each other.
+pub fn is_stable(samples: &[f64], window: usize, max_noise: f64) -> bool {
+    if window == 0 || samples.len() < window {
+        return false;
+    }
+    peak_to_peak(&samples[samples.len() - window..]) < max_noise
+}
+/// One step of an exponential moving average; `alpha` is the weight of the new sample.
+pub fn ema(previous: f64, sample: f64, alpha: f64) -> f64 {
+    previous + alpha * (sample - previous)
+}
+/// Least-squares change per sample.
+pub fn slope(samples: &[f64]) -> Option<f64> {
+    if samples.len() < 2 {
+        return None;
+    }
+    let n = samples.len() as f64;
+    let mean_t = (n - 1.) / 2.;
+    let mean_w = samples.iter().sum::<f64>() / n;
+    let (covariance, variance) =
+        samples
+            .iter()
+            .enumerate()
+            .fold((0., 0.), |(covariance, variance), (i, w)| {
+                let dt = i as f64 - mean_t;
+                (covariance + dt * (w - mean_w), variance + dt * dt)
+            });
+    Some(covariance / variance)
+}
+/// Sign changes of `samples` around their mean; exact-mean samples don't count.
+pub fn mean_crossings(samples: &[f64]) -> usize {
+    let Some(mean) = mean(samples) else {
+        return 0;
+    };
+    let mut previous = None;
+    let mut crossings = 0;
+    for &sample in samples {
+        if sample == mean {
+            continue;
+        }
+        let above = sample > mean;
+        if previous.is_some_and(|was_above| was_above != above) {
+            crossings += 1;
+        }
+        previous = Some(above);
+    }
+    crossings
+}
+/// Least-squares slope of `(x, y)` points; zero when `x` doesn't vary.
+pub fn slope_xy(points: &[(f64, f64)]) -> f64 {
+    if points.is_empty() {
+        return 0.;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if variance == 0. {
+        return 0.;
+    }
+    covariance / variance
+}
+/// The change from `last_stable` to `current` if it exceeds `max_noise`. Bipolar cells
+/// compare magnitudes so tension and compression both count as load.
+pub fn action_delta(current: f64, last_stable: f64, max_noise: f64, bipolar: bool) -> Option<f64> {
+    let delta = if bipolar {
+        current.abs() - last_stable.abs()
+    } else {
+        current - last_stable
+    };
+    (delta.abs() > max_noise).then_some(delta)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stability_over_latest_window() {
+        let samples = [50., 10., 10.4, 9.8];
+        assert!(is_stable(&samples, 3, 1.));
+        assert!(!is_stable(&samples, 4, 1.));
+        assert!(!is_stable(&samples, 5, 1.));
+        assert!(!is_stable(&samples, 0, 1.));
+        assert_eq!(peak_to_peak(&samples), 40.2);
+    }
+    #[test]
+    fn mean_median_and_slope() {
+        let mut samples = [4., 1., 3., 2.];
+        assert_eq!(mean(&samples), Some(2.5));
+        assert_eq!(median(&mut samples), Some(2.5));
+        assert_eq!(median(&mut [7., 1., 3.]), Some(3.));
+        assert_eq!(median(&mut []), None);
+        assert_eq!(slope(&[10., 12., 14., 16.]), Some(2.));
+        assert_eq!(slope(&[1.]), None);
+    }
+    #[test]
+    fn ema_steps_toward_sample() {
+        assert_eq!(ema(0., 100., 0.25), 25.);
+        assert_eq!(ema(25., 100., 1.), 100.);
+        assert_eq!(ema(25., 100., 0.), 25.);
+    }
+    #[test]
+    fn crossings_around_mean() {
+        assert_eq!(mean_crossings(&[1., 3., 1., 3., 1.]), 4);
+        assert_eq!(mean_crossings(&[1., 2., 3., 4.]), 1);
+        assert_eq!(mean_crossings(&[2., 2.]), 0);
+        assert_eq!(mean_crossings(&[]), 0);
+    }
+    #[test]
+    fn action_deltas() {
+        assert_eq!(action_delta(90., 100., 1., false), Some(-10.));
+        assert_eq!(action_delta(100.5, 100., 1., false), None);
+        assert_eq!(action_delta(-120., 100., 1., true), Some(20.));
+        assert_eq!(action_delta(-120., 100., 1., false), Some(-220.));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::error::Error;
+use crate::scale::{Scale, Weight};
+use crate::source::RawSource;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresenceEvent {
+    Placed(f64),
+    Removed,
+}
+/// Turns a weight stream into one `Placed`/`Removed` pair per item. An item counts as
+/// placed once it has stayed above `enter_threshold` for `debounce` and reads stable, and
+/// as removed once it has stayed below `exit_threshold` for `debounce`.
+pub struct PresenceTracker {
+    enter_threshold: f64,
+    exit_threshold: f64,
+    debounce: Duration,
+    occupied: bool,
+    crossed_at: Option<Instant>,
+}
+impl PresenceTracker {
+    pub fn new(enter_threshold: f64, exit_threshold: f64, debounce: Duration) -> Self {
+        Self {
+            enter_threshold,
+            exit_threshold: exit_threshold.min(enter_threshold),
+            debounce,
+            occupied: false,
+            crossed_at: None,
+        }
+    }
+    pub fn sample<S: RawSource>(
+        &mut self,
+        scale: &mut Scale<S>,
+    ) -> Result<Option<PresenceEvent>, Error> {
+        let weight = scale.get_weight()?;
+        Ok(self.update(&weight, Instant::now()))
+    }
+    pub fn update(&mut self, weight: &Weight, at: Instant) -> Option<PresenceEvent> {
+        let amount = weight.get_amount();
+        let crossing = if self.occupied {
+            amount < self.exit_threshold
+        } else {
+            amount > self.enter_threshold
+        };
+        if !crossing {
+            self.crossed_at = None;
+            return None;
+        }
+        let since = *self.crossed_at.get_or_insert(at);
+        if at.duration_since(since) < self.debounce {
+            return None;
+        }
+        if self.occupied {
+            self.occupied = false;
+            self.crossed_at = None;
+            Some(PresenceEvent::Removed)
+        } else if weight.is_stable() {
+            self.occupied = true;
+            self.crossed_at = None;
+            Some(PresenceEvent::Placed(amount))
+        } else {
+            None
+        }
+    }
+    pub fn is_occupied(&self) -> bool {
+        self.occupied
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn wobbling_placement_is_reported_once() {
+        let mut tracker = PresenceTracker::new(50., 20., Duration::from_millis(300));
+        let start = Instant::now();
+        let script = [
+            Weight::Stable(0.),
+            Weight::Unstable(120.),
+            Weight::Unstable(70.),
+            Weight::Unstable(135.),
+            Weight::Unstable(95.),
+            Weight::Stable(125.),
+            Weight::Unstable(140.),
+            Weight::Stable(124.),
+            Weight::Unstable(30.),
+            Weight::Stable(125.),
+            Weight::Unstable(10.),
+            Weight::Stable(0.),
+            Weight::Stable(0.),
+            Weight::Stable(0.),
+            Weight::Stable(0.),
+        ];
+        let events: Vec<PresenceEvent> = script
+            .iter()
+            .enumerate()
+            .filter_map(|(i, weight)| {
+                tracker.update(weight, start + Duration::from_millis(100 * i as u64))
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![PresenceEvent::Placed(125.), PresenceEvent::Removed]
+        );
+        assert!(!tracker.is_occupied());
+    }
+    #[test]
+    fn brief_bump_is_not_a_placement() {
+        let mut tracker = PresenceTracker::new(50., 20., Duration::from_millis(300));
+        let start = Instant::now();
+        for (i, weight) in [Weight::Stable(80.), Weight::Stable(0.), Weight::Stable(0.)]
+            .iter()
+            .enumerate()
+        {
+            let at = start + Duration::from_millis(200 * i as u64);
+            assert_eq!(tracker.update(weight, at), None);
+        }
+    }
+}
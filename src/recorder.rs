@@ -0,0 +1,105 @@
+use crate::error::Error;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Appends `epoch_ms,raw,weight` lines to a file kept under `max_bytes`. When a line would
+/// exceed the cap, the oldest quarter is dropped and the file rewritten, so the newest
+/// readings always survive a crash or restart.
+pub struct FlightRecorder {
+    path: PathBuf,
+    max_bytes: usize,
+    lines: VecDeque<String>,
+    bytes: usize,
+}
+impl FlightRecorder {
+    /// Picks up where an existing file left off, so a restart keeps the readings that led
+    /// up to it.
+    pub fn create(path: &Path, max_bytes: usize) -> Result<Self, Error> {
+        let existing = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let lines: VecDeque<String> = existing.lines().map(|line| format!("{line}\n")).collect();
+        let mut recorder = Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            bytes: lines.iter().map(String::len).sum(),
+            lines,
+        };
+        if recorder.bytes > max_bytes {
+            recorder.trim()?;
+        } else {
+            recorder.rewrite()?;
+        }
+        Ok(recorder)
+    }
+    pub fn record(&mut self, raw: f64, weight: f64) -> Result<(), Error> {
+        let epoch_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|since| since.as_millis())
+            .unwrap_or(0);
+        let line = format!("{epoch_ms},{raw},{weight}\n");
+        self.bytes += line.len();
+        self.lines.push_back(line);
+        if self.bytes <= self.max_bytes {
+            let mut file = OpenOptions::new().append(true).open(&self.path)?;
+            file.write_all(self.lines.back().unwrap().as_bytes())?;
+            return Ok(());
+        }
+        self.trim()
+    }
+    fn trim(&mut self) -> Result<(), Error> {
+        while self.bytes > self.max_bytes * 3 / 4
+            && let Some(oldest) = self.lines.pop_front()
+        {
+            self.bytes -= oldest.len();
+        }
+        self.rewrite()
+    }
+    fn rewrite(&self) -> Result<(), Error> {
+        std::fs::write(
+            &self.path,
+            self.lines.iter().map(String::as_str).collect::<String>(),
+        )?;
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn stays_under_cap_keeping_newest() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("recorder-{}.csv", std::process::id()));
+        let mut recorder = FlightRecorder::create(&path, 256)?;
+        for i in 0..100 {
+            recorder.record(i as f64, i as f64 * 2.)?;
+            assert!(std::fs::metadata(&path)?.len() <= 256);
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let last = contents.lines().last().unwrap();
+        assert!(last.ends_with(",99,198"));
+        assert!(!contents.contains(",0,0\n"));
+        Ok(())
+    }
+    #[test]
+    fn restart_keeps_previous_readings() -> Result<(), Error> {
+        let path =
+            std::env::temp_dir().join(format!("recorder-restart-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        FlightRecorder::create(&path, 1024)?.record(1., 2.)?;
+        let mut restarted = FlightRecorder::create(&path, 1024)?;
+        restarted.record(3., 6.)?;
+        let contents = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(",1,2"));
+        assert!(lines[1].ends_with(",3,6"));
+        Ok(())
+    }
+}
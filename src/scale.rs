@@ -1,44 +1,110 @@
+use crate::calibration::CalibrationReport;
+#[cfg(feature = "temperature")]
+use crate::calibration::TempCalibration;
 use crate::error::Error;
-use log::info;
+use crate::filter::{Filter, FilterState};
+use crate::manager::is_discovered;
+use crate::math::{self, peak_to_peak};
+use crate::recorder::FlightRecorder;
+use crate::settings::{Settings, SettleMode, StabilityMode};
+use crate::source::RawSource;
+use crate::unit::{Milligrams, WeightUnit};
+use log::{info, log, warn};
 use menu::action::Action;
 use menu::device::Device;
 use menu::libra::{Config, Libra};
 use menu::read::Read;
+#[cfg(feature = "temperature")]
+use phidget::devices::TemperatureSensor;
 use phidget::{Phidget, devices::VoltageRatioInput};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::path::Path;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 #[cfg(feature = "find_phidgets")]
 const PHIDGET_VENDOR_ID: u16 = 1730;
 #[cfg(feature = "find_phidgets")]
 const PHIDGET_PRODUCT_ID: u16 = 59;
 
+#[cfg(feature = "find_phidgets")]
+trait UsbDevice {
+    fn vendor_product(&self) -> Result<(u16, u16), Error>;
+    fn serial_number(&self) -> Result<Option<String>, Error>;
+}
+#[cfg(feature = "find_phidgets")]
+impl UsbDevice for rusb::Device<rusb::GlobalContext> {
+    fn vendor_product(&self) -> Result<(u16, u16), Error> {
+        let device_desc = self.device_descriptor()?;
+        Ok((device_desc.vendor_id(), device_desc.product_id()))
+    }
+    fn serial_number(&self) -> Result<Option<String>, Error> {
+        let device_desc = self.device_descriptor()?;
+        let handle = self.open()?;
+        match device_desc.serial_number_string_index() {
+            Some(id) => {
+                handle.read_string_descriptor_ascii(id)?;
+                Ok(Some(handle.read_string_descriptor_ascii(id)?))
+            }
+            None => Ok(None),
+        }
+    }
+}
+#[cfg(feature = "find_phidgets")]
+fn collect_phidget_ids(
+    devices: impl IntoIterator<Item = impl UsbDevice>,
+) -> Result<Vec<isize>, Error> {
+    let mut connected_phidgets: Vec<isize> = Vec::with_capacity(4);
+    for device in devices {
+        match device.vendor_product() {
+            Ok((PHIDGET_VENDOR_ID, PHIDGET_PRODUCT_ID)) => {}
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("Skipping USB device with unreadable descriptor: {e}");
+                continue;
+            }
+        }
+        match device.serial_number() {
+            Ok(Some(sn)) => match sn.parse() {
+                Ok(id) => connected_phidgets.push(id),
+                Err(_) => warn!("Skipping phidget with non-numeric serial {sn:?}"),
+            },
+            Ok(None) => {}
+            Err(Error::UsbPermission) => return Err(Error::UsbPermission),
+            Err(e) => warn!("Skipping phidget that couldn't be opened: {e}"),
+        }
+    }
+    Ok(connected_phidgets)
+}
+pub enum ConnectOutcome {
+    Connected(Box<Scale>),
+    /// The phidget isn't plugged in.
+    Absent(DisconnectedScale),
+    /// The phidget was discovered but couldn't be opened.
+    Failed(DisconnectedScale, Error),
+}
+#[derive(Clone)]
 pub struct DisconnectedScale {
     config: Config,
     device: Device,
+    settings: Settings,
 }
 impl DisconnectedScale {
     #[cfg(feature = "find_phidgets")]
     pub fn get_connected_phidget_ids() -> Result<Vec<isize>, Error> {
-        let mut connected_phidgets: Vec<isize> = Vec::with_capacity(4);
-        for device in rusb::devices()?.iter() {
-            let device_desc = device.device_descriptor()?;
-            if device_desc.vendor_id() == PHIDGET_VENDOR_ID
-                && device_desc.product_id() == PHIDGET_PRODUCT_ID
-            {
-                let handle = device.open()?;
-                if let Some(id) = device_desc.serial_number_string_index() {
-                    handle.read_string_descriptor_ascii(id)?;
-                    let sn = handle.read_string_descriptor_ascii(id)?;
-                    connected_phidgets.push(sn.parse().map_err(|_| Error::ParseInt)?);
-                }
-            }
-        }
-        Ok(connected_phidgets)
+        collect_phidget_ids(rusb::devices()?.iter())
     }
     pub fn new(config: Config, device: Device) -> Self {
-        Self { config, device }
+        Self {
+            config,
+            device,
+            settings: Settings::default(),
+        }
+    }
+    pub fn with_settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
     }
     pub fn from_libra_menu(libra: Libra) -> Self {
         Self::new(libra.config, libra.device)
@@ -50,22 +116,141 @@ impl DisconnectedScale {
             .collect())
     }
     pub fn connect(self) -> Result<Scale, Error> {
-        Scale::new(self.config, self.device)
+        self.settings.validate(&self.config)?;
+        Scale::with_settings(self.config, self.device, self.settings)
+    }
+    #[cfg(feature = "find_phidgets")]
+    pub fn try_connect(self) -> ConnectOutcome {
+        match Self::get_connected_phidget_ids() {
+            Ok(connected_ids) => self.try_connect_among(&connected_ids),
+            Err(e) => ConnectOutcome::Failed(self, e),
+        }
+    }
+    pub fn try_connect_among(self, connected_ids: &[isize]) -> ConnectOutcome {
+        self.try_connect_with(connected_ids, DisconnectedScale::connect)
+    }
+    fn try_connect_with(
+        self,
+        connected_ids: &[isize],
+        connector: fn(DisconnectedScale) -> Result<Scale, Error>,
+    ) -> ConnectOutcome {
+        if !is_discovered(self.config.phidget_id, connected_ids) {
+            return ConnectOutcome::Absent(self);
+        }
+        match connector(self.clone()) {
+            Ok(scale) => ConnectOutcome::Connected(Box::new(scale)),
+            Err(e) => ConnectOutcome::Failed(self, e),
+        }
+    }
+    /// Retries [`DisconnectedScale::connect`] until the phidget enumerates or
+    /// `overall_timeout` passes, returning the last connection error.
+    pub fn connect_wait(
+        self,
+        overall_timeout: Duration,
+        retry_interval: Duration,
+    ) -> Result<Scale, Error> {
+        self.connect_wait_with(overall_timeout, retry_interval, DisconnectedScale::connect)
+    }
+    fn connect_wait_with(
+        self,
+        overall_timeout: Duration,
+        retry_interval: Duration,
+        mut connector: impl FnMut(DisconnectedScale) -> Result<Scale, Error>,
+    ) -> Result<Scale, Error> {
+        self.settings.validate(&self.config)?;
+        let start_time = Instant::now();
+        let mut attempt = 1;
+        loop {
+            match connector(self.clone()) {
+                Ok(scale) => return Ok(scale),
+                Err(e) if start_time.elapsed() + retry_interval < overall_timeout => {
+                    warn!("Connecting {} failed (attempt {attempt}): {e}", self.device);
+                    attempt += 1;
+                    sleep(retry_interval);
+                }
+                Err(e) => return Err(e),
+            }
+        }
     }
     pub fn get_device(&self) -> Device {
         self.device.clone()
     }
+    pub fn get_config(&self) -> Config {
+        self.config.clone()
+    }
+    pub fn connect_with_source<S: RawSource>(self, source: S) -> Result<Scale<S>, Error> {
+        Scale::from_parts(source, self.config, self.device, self.settings)
+    }
+    #[cfg(test)]
+    pub(crate) fn connect_unopened(self) -> Result<Scale, Error> {
+        self.connect_with_source(VoltageRatioInput::new())
+    }
 }
-pub struct Scale {
-    vin: VoltageRatioInput,
+pub struct Scale<S = VoltageRatioInput> {
+    source: S,
     config: Config,
     device: Device,
+    settings: Settings,
     weight_buffer: Vec<f64>,
     last_stable_weight: Option<f64>,
+    verified_with: Option<f64>,
+    pending_action: Option<(f64, usize)>,
+    capacity_warned: bool,
+    on_stable: Option<Box<dyn FnMut(f64) + Send>>,
+    alarms: Vec<(AlarmSpec, bool)>,
+    was_stable: bool,
+    read_cache: Cell<Option<(Instant, f64)>>,
+    #[cfg(feature = "temperature")]
+    temperature: Option<TemperatureSensor>,
+    /// With the config offset when it was set; later offset changes apply as a delta.
+    #[cfg(feature = "temperature")]
+    temp_calibration: Option<(TempCalibration, f64)>,
+    /// Zero shift per degree and the temperature it's measured from.
+    #[cfg(feature = "temperature")]
+    thermal_compensation: Option<(f64, f64)>,
+    #[cfg(all(test, feature = "temperature"))]
+    scripted_temperature: Option<f64>,
+    bias: f64,
+    transform: Option<Box<dyn Fn(f64) -> f64 + Send>>,
+    ema: Option<(f64, f64)>,
+    displayed: Option<f64>,
+    tare_weight: f64,
+    recorder: Option<RefCell<FlightRecorder>>,
+    /// Bits of the raw reading behind the last buffered sample.
+    last_raw: Option<u64>,
+    stable_since: Option<Instant>,
+    /// Level idling started at; while set the source samples at `settings.idle_period`
+    /// and `config` keeps the configured period.
+    idling: Option<f64>,
+    offset_ramp: Option<(f64, Instant)>,
+    leak_history: VecDeque<(Instant, f64, bool)>,
+    paused: bool,
+    last_served: Option<f64>,
+    filter: FilterState,
 }
 impl Scale {
     pub fn new(config: Config, device: Device) -> Result<Self, Error> {
+        Self::with_settings(config, device, Settings::default())
+    }
+    pub fn with_settings(
+        config: Config,
+        device: Device,
+        settings: Settings,
+    ) -> Result<Self, Error> {
+        settings.validate(&config)?;
         let mut vin = VoltageRatioInput::new();
+        if let Some(server) = &settings.phidget_server {
+            match phidget::net::add_server(
+                &server.name,
+                &server.address,
+                server.port,
+                &server.password,
+            ) {
+                Ok(()) | Err(phidget::ReturnCode::Duplicate) => {}
+                Err(e) => return Err(Error::Phidget(e)),
+            }
+            vin.set_remote(true).map_err(Error::Phidget)?;
+        }
         vin.set_channel(config.load_cell_id)
             .map_err(Error::Phidget)?;
         vin.set_serial_number(config.phidget_id)
@@ -80,74 +265,762 @@ impl Scale {
             vin.channel().map_err(Error::Phidget)?
         );
         sleep(Duration::from_secs(1));
+        #[cfg_attr(not(feature = "temperature"), allow(unused_mut))]
+        let mut scale = Self::from_parts(vin, config, device, settings)?;
+        #[cfg(feature = "temperature")]
+        if let Some(channel) = scale.settings.temperature_channel {
+            scale.attach_temperature(channel)?;
+        }
+        Ok(scale)
+    }
+    pub fn restart(&mut self) -> Result<(), Error> {
+        self.source.close().map_err(Error::Phidget)?;
+        self.source
+            .open_wait(Duration::from_secs(5))
+            .map_err(Error::Phidget)?;
+        self.reset_state();
+        sleep(Duration::from_secs(2));
+        Ok(())
+    }
+    pub fn is_connected(&self) -> bool {
+        self.source.is_attached().unwrap_or(false)
+    }
+    pub fn phidget_info(&self) -> Result<PhidgetInfo, Error> {
+        Ok(PhidgetInfo {
+            serial: self.source.serial_number()?,
+            channel: self.source.channel()?,
+            attached: self.source.is_attached()?,
+        })
+    }
+    pub fn disconnect(mut self) -> Result<(), Error> {
+        self.source.close()?;
+        Ok(())
+    }
+}
+impl<S: RawSource> Scale<S> {
+    pub fn from_source(source: S, config: Config, device: Device) -> Result<Self, Error> {
+        Self::from_parts(source, config, device, Settings::default())
+    }
+    fn from_parts(
+        source: S,
+        config: Config,
+        device: Device,
+        settings: Settings,
+    ) -> Result<Self, Error> {
+        settings.validate(&config)?;
+        if settings.idle_after.is_some() {
+            check_sample_period(
+                settings.idle_period,
+                (source.min_sample_period()?, source.max_sample_period()?),
+            )?;
+        }
         let buffer_length = config.buffer_length;
         Ok(Self {
-            vin,
+            source,
             config,
             device,
+            settings,
             weight_buffer: Vec::with_capacity(buffer_length),
             last_stable_weight: None,
+            verified_with: None,
+            pending_action: None,
+            capacity_warned: false,
+            on_stable: None,
+            alarms: Vec::new(),
+            was_stable: false,
+            read_cache: Cell::new(None),
+            #[cfg(feature = "temperature")]
+            temperature: None,
+            #[cfg(feature = "temperature")]
+            temp_calibration: None,
+            #[cfg(feature = "temperature")]
+            thermal_compensation: None,
+            #[cfg(all(test, feature = "temperature"))]
+            scripted_temperature: None,
+            bias: 0.,
+            transform: None,
+            ema: None,
+            displayed: None,
+            tare_weight: 0.,
+            recorder: None,
+            last_raw: None,
+            stable_since: None,
+            idling: None,
+            offset_ramp: None,
+            leak_history: VecDeque::new(),
+            paused: false,
+            last_served: None,
+            filter: FilterState::default(),
         })
     }
-    pub fn restart(&mut self) -> Result<(), Error> {
-        self.vin.close().map_err(Error::Phidget)?;
-        self.vin
-            .open_wait(Duration::from_secs(5))
-            .map_err(Error::Phidget)?;
+    pub fn supported_sample_interval(&self) -> Result<(Duration, Duration), Error> {
+        Ok((
+            self.source.min_sample_period()?,
+            self.source.max_sample_period()?,
+        ))
+    }
+    pub fn set_sample_period(&mut self, period: Duration) -> Result<(), Error> {
+        check_sample_period(period, self.supported_sample_interval()?)?;
+        if self.idling.is_none() {
+            self.source.set_sample_period(period)?;
+        }
+        self.config.phidget_sample_period = period;
+        Ok(())
+    }
+    pub fn reset_state(&mut self) {
         self.weight_buffer.clear();
+        self.ema = None;
+        self.displayed = None;
         self.last_stable_weight = None;
-        sleep(Duration::from_secs(2));
-        Ok(())
+        self.pending_action = None;
+        self.capacity_warned = false;
+        self.was_stable = false;
+        self.filter.reset();
+        self.leak_history.clear();
+        self.last_served = None;
+    }
+    /// Stops [`Scale::get_weight`] reading or buffering, returning [`Error::Paused`], while
+    /// the phidget stays open.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    /// Resumes reading with a fresh buffer, since anything buffered before the pause is stale.
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            self.reset_state();
+        }
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// Smoothing applied to each reading in [`Scale::get_weight`], replacing any previous one.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = FilterState::new(filter);
+    }
+    pub fn get_filter(&self) -> Filter {
+        self.filter.filter()
     }
     pub fn get_device(&self) -> Device {
         self.device.clone()
     }
+    /// Opens a temperature sensor on the same hub, read via [`Scale::temperature`].
+    #[cfg(feature = "temperature")]
+    pub fn attach_temperature(&mut self, channel: i32) -> Result<(), Error> {
+        let mut sensor = TemperatureSensor::new();
+        if self.settings.phidget_server.is_some() {
+            sensor.set_remote(true)?;
+        }
+        sensor.set_channel(channel)?;
+        sensor.set_serial_number(self.config.phidget_id)?;
+        sensor.open_wait(Duration::from_secs(5))?;
+        self.temperature = Some(sensor);
+        Ok(())
+    }
+    #[cfg(feature = "temperature")]
+    pub fn temperature(&self) -> Result<f64, Error> {
+        #[cfg(test)]
+        if let Some(temperature) = self.scripted_temperature {
+            return Ok(temperature);
+        }
+        match &self.temperature {
+            Some(sensor) => Ok(sensor.temperature()?),
+            None => Err(Error::NoTemperature),
+        }
+    }
+    /// Replaces the fixed gain and offset with one interpolated by the attached sensor's
+    /// temperature. Readings fall back to the config while the sensor can't be read. Tares
+    /// and offset changes made afterwards shift the interpolated zero; a new fixed
+    /// calibration replaces it.
+    #[cfg(feature = "temperature")]
+    pub fn set_temp_calibration(&mut self, calibration: Option<TempCalibration>) {
+        self.temp_calibration = calibration.map(|c| (c, self.config.offset));
+    }
+    /// Subtracts `coefficient` grams per degree the attached sensor has moved since now,
+    /// such as [`crate::drift::DriftMonitor::thermal_coefficient`]. Tares absorb the
+    /// shift at the time, so it never needs re-referencing. A temperature calibration
+    /// already models the zero, so it takes precedence.
+    #[cfg(feature = "temperature")]
+    pub fn set_thermal_coefficient(&mut self, coefficient: Option<f64>) -> Result<(), Error> {
+        self.thermal_compensation = match coefficient {
+            Some(coefficient) => Some((coefficient, self.temperature()?)),
+            None => None,
+        };
+        Ok(())
+    }
+    fn calibrated(&self, raw: f64) -> f64 {
+        self.zeroed(raw) + self.ramp_correction()
+    }
+    /// Weight from the active calibration and zero, before any tare ramp.
+    fn zeroed(&self, raw: f64) -> f64 {
+        #[cfg(feature = "temperature")]
+        if let Some((calibration, base_offset)) = &self.temp_calibration
+            && let Ok(temperature) = self.temperature()
+        {
+            return calibration.at(temperature).apply(raw) - (self.config.offset - base_offset);
+        }
+        #[cfg(feature = "temperature")]
+        if let Some((coefficient, reference)) = self.thermal_compensation
+            && let Ok(temperature) = self.temperature()
+        {
+            return self.raw_to_weight(raw) - coefficient * (temperature - reference);
+        }
+        self.raw_to_weight(raw)
+    }
+    /// What's left of the step from the previous tare's offset while `tare_ramp` runs.
+    fn ramp_correction(&self) -> f64 {
+        let (Some((previous, started)), Some(ramp)) = (self.offset_ramp, self.settings.tare_ramp)
+        else {
+            return 0.;
+        };
+        let progress = started.elapsed().as_secs_f64() / ramp.as_secs_f64();
+        if progress >= 1. {
+            return 0.;
+        }
+        (self.config.offset - previous) * (1. - progress)
+    }
+    fn set_tare_offset(&mut self, offset: f64) {
+        if self.settings.tare_ramp.is_some() {
+            let previous = self.config.offset - self.ramp_correction();
+            self.offset_ramp = Some((previous, Instant::now()));
+        }
+        self.config.offset = offset;
+        self.verified_with = None;
+        // The tared reading is the new zero; re-baseline so the step isn't reported as an
+        // action.
+        self.weight_buffer.clear();
+        self.ema = None;
+        self.pending_action = None;
+        self.was_stable = false;
+        self.filter.reset();
+        self.leak_history.clear();
+        if self.last_stable_weight.is_some() {
+            self.last_stable_weight = Some(self.transform_weight(0.) - self.bias);
+        }
+    }
     pub fn get_raw_reading(&self) -> Result<f64, Error> {
-        self.vin.voltage_ratio().map_err(Error::Phidget)
+        self.source.read()
+    }
+    /// Median of three back-to-back raw reads, which drops a single glitched read far more
+    /// cheaply than a full filter.
+    pub fn get_raw_reading_deglitched(&self) -> Result<f64, Error> {
+        let mut reads = [
+            self.get_raw_reading()?,
+            self.get_raw_reading()?,
+            self.get_raw_reading()?,
+        ];
+        Ok(math::median(&mut reads).unwrap_or(reads[0]))
+    }
+    fn settle_raw_reading(&self) -> Result<f64, Error> {
+        if self.settings.deglitch_raw {
+            self.get_raw_reading_deglitched()
+        } else {
+            self.get_raw_reading()
+        }
+    }
+    /// Fills `out` with raw readings at the sample period without allocating. A failed
+    /// read ends the fill early; the count says how much of `out` is valid, and the error
+    /// is only returned if nothing was read.
+    pub fn read_into(&self, out: &mut [f64]) -> Result<usize, Error> {
+        for (i, slot) in out.iter_mut().enumerate() {
+            if i > 0 {
+                sleep(self.sample_period());
+            }
+            match self.get_raw_reading() {
+                Ok(raw) => *slot = raw,
+                Err(e) if i == 0 => return Err(e),
+                Err(e) => {
+                    warn!("{}: read_into stopped after {i} readings: {e}", self.device);
+                    return Ok(i);
+                }
+            }
+        }
+        Ok(out.len())
     }
     fn get_reading(&self) -> Result<f64, Error> {
-        self.get_raw_reading()
-            .map(|r| r * self.config.gain - self.config.offset)
+        Ok(self.weigh_raw(self.get_raw_reading()?))
+    }
+    /// Corrects `raw` and writes both to the flight recorder.
+    fn weigh_raw(&self, raw: f64) -> f64 {
+        let weight = self.corrected(raw);
+        if let Some(recorder) = &self.recorder
+            && let Err(e) = recorder.borrow_mut().record(raw, weight)
+        {
+            warn!("{}: flight recorder write failed: {e}", self.device);
+        }
+        weight
+    }
+    /// Records every reading to `path`, keeping the file under `max_bytes`.
+    pub fn enable_recorder(&mut self, path: &Path, max_bytes: usize) -> Result<(), Error> {
+        self.recorder = Some(RefCell::new(FlightRecorder::create(path, max_bytes)?));
+        Ok(())
+    }
+    fn transformed(&self, raw: f64) -> f64 {
+        self.transform_weight(self.calibrated(raw))
+    }
+    fn transform_weight(&self, weight: f64) -> f64 {
+        match &self.transform {
+            Some(transform) => transform(weight),
+            None => weight,
+        }
+    }
+    fn corrected(&self, raw: f64) -> f64 {
+        self.transformed(raw) - self.bias
+    }
+    /// Custom correction applied to every reading after gain and offset.
+    pub fn set_transform(&mut self, f: Box<dyn Fn(f64) -> f64 + Send>) {
+        self.transform = Some(f);
+    }
+    pub fn raw_to_weight(&self, raw: f64) -> f64 {
+        raw * self.config.gain - self.config.offset
+    }
+    pub fn weight_to_raw(&self, weight: f64) -> f64 {
+        (weight + self.config.offset) / self.config.gain
+    }
+    pub fn get_instant_weight(&self) -> Result<f64, Error> {
+        let Some(ttl) = self.settings.read_cache_ttl else {
+            return self.get_reading();
+        };
+        if let Some((read_at, raw)) = self.read_cache.get()
+            && read_at.elapsed() < ttl
+        {
+            return Ok(self.corrected(raw));
+        }
+        let raw = self.get_raw_reading()?;
+        self.read_cache.set(Some((Instant::now(), raw)));
+        Ok(self.corrected(raw))
+    }
+    /// The one place the buffer's target length is read; the buffer itself is trimmed
+    /// to it on the next update, so a changed `buffer_length` can't strand stability.
+    fn buffer_length(&self) -> usize {
+        self.config.buffer_length.max(1)
+    }
+    fn is_buffer_full(&self) -> bool {
+        self.weight_buffer.len() >= self.buffer_length()
     }
     fn update_buffer(&mut self, weight: f64) {
-        if self.weight_buffer.len() < self.config.buffer_length {
-            self.weight_buffer.push(weight);
-        } else {
-            self.weight_buffer.remove(0);
-            self.weight_buffer.push(weight);
+        let excess = (self.weight_buffer.len() + 1).saturating_sub(self.buffer_length());
+        self.weight_buffer.drain(..excess);
+        self.weight_buffer.push(weight);
+        if let StabilityMode::DualEma {
+            fast_alpha,
+            slow_alpha,
+        } = self.settings.stability_mode
+        {
+            self.ema = Some(match self.ema {
+                Some((fast, slow)) => (
+                    math::ema(fast, weight, fast_alpha),
+                    math::ema(slow, weight, slow_alpha),
+                ),
+                None => (weight, weight),
+            });
         }
     }
     fn is_stable(&self) -> bool {
-        if self.weight_buffer.len() != self.config.buffer_length {
-            return false;
+        if let StabilityMode::DualEma { .. } = self.settings.stability_mode {
+            return self
+                .ema
+                .is_some_and(|(fast, slow)| (fast - slow).abs() < self.config.max_noise);
         }
-        let max = self
-            .weight_buffer
-            .iter()
-            .fold(f64::NEG_INFINITY, |a, &b| a.max(b));
-        let min = self
-            .weight_buffer
+        math::is_stable(
+            &self.weight_buffer,
+            self.stability_window(),
+            self.config.max_noise,
+        )
+    }
+    fn stability_window(&self) -> usize {
+        match self.settings.stability_window {
+            Some(window) => window.min(self.buffer_length()),
+            None => self.buffer_length(),
+        }
+    }
+    /// Mean of the samples that made the buffer stable, so one sample's noise doesn't
+    /// become the action baseline. In `DualEma` mode the buffer can still hold samples
+    /// from before a step, so the fast EMA is the level instead.
+    fn stable_level(&self) -> Option<f64> {
+        if let StabilityMode::DualEma { .. } = self.settings.stability_mode {
+            return self.ema.map(|(fast, _)| fast);
+        }
+        let window = self.stability_window().min(self.weight_buffer.len());
+        math::mean(&self.weight_buffer[self.weight_buffer.len() - window..])
+    }
+    /// Stability plus which way the buffered trend moved; drift within `max_noise` across
+    /// the buffer counts as flat.
+    pub fn settle_state(&self) -> SettleState {
+        let span = self.weight_buffer.len().saturating_sub(1) as f64;
+        let change = math::slope(&self.weight_buffer).unwrap_or(0.) * span;
+        let direction = if change.abs() < self.config.max_noise {
+            Direction::Flat
+        } else if change > 0. {
+            Direction::Rising
+        } else {
+            Direction::Falling
+        };
+        SettleState {
+            stable: self.is_stable(),
+            direction,
+        }
+    }
+    /// A full buffer that isn't stable yet keeps swinging back and forth across its mean,
+    /// as a springy mount does, rather than drifting one way.
+    pub fn is_oscillating(&self) -> bool {
+        self.is_buffer_full()
+            && peak_to_peak(&self.weight_buffer) >= self.config.max_noise
+            && math::mean_crossings(&self.weight_buffer) >= MIN_OSCILLATION_CROSSINGS
+    }
+    pub fn buffer_snapshot(&self) -> Vec<f64> {
+        self.weight_buffer.clone()
+    }
+    pub fn buffer_stats(&self) -> Option<BufferStats> {
+        if !self.is_buffer_full() {
+            return None;
+        }
+        self.buffer_stats_partial()
+    }
+    /// Like [`Scale::buffer_stats`] but over whatever is buffered (at least two samples),
+    /// so the numbers are less trustworthy until the buffer fills.
+    pub fn buffer_stats_partial(&self) -> Option<BufferStats> {
+        BufferStats::from_samples(&self.weight_buffer)
+    }
+    /// Reads the scale and returns the loss rate in units per second if, over the last
+    /// `window`, the weight has been sinking faster than `rate_threshold` while staying
+    /// stable, i.e. too slowly for [`Scale::check_for_action`] to see a served event.
+    pub fn detect_leak(&mut self, rate_threshold: f64, window: Duration) -> Option<f64> {
+        match self.get_weight() {
+            Ok(weight) => self.leak_rate(&weight, Instant::now(), rate_threshold, window),
+            Err(e) => {
+                warn!("{}: leak check read failed: {e}", self.device);
+                None
+            }
+        }
+    }
+    fn leak_rate(
+        &mut self,
+        weight: &Weight,
+        at: Instant,
+        rate_threshold: f64,
+        window: Duration,
+    ) -> Option<f64> {
+        self.leak_history
+            .push_back((at, weight.get_amount(), weight.is_stable()));
+        while self
+            .leak_history
+            .front()
+            .is_some_and(|(oldest, ..)| at.duration_since(*oldest) > window)
+        {
+            self.leak_history.pop_front();
+        }
+        let (oldest, ..) = *self.leak_history.front()?;
+        // Samples a little short of the window still count as covering it.
+        if at.duration_since(oldest) < window.mul_f64(0.9) {
+            return None;
+        }
+        // Any unstable sample or step past the noise band is a discrete event, not a leak.
+        let mut previous = None;
+        for &(_, weight, stable) in &self.leak_history {
+            if !stable || previous.is_some_and(|p: f64| (weight - p).abs() > self.config.max_noise)
+            {
+                return None;
+            }
+            previous = Some(weight);
+        }
+        let points: Vec<(f64, f64)> = self
+            .leak_history
             .iter()
-            .fold(f64::INFINITY, |a, &b| a.min(b));
-        max - min < self.config.max_noise
+            .map(|(t, w, _)| (t.duration_since(oldest).as_secs_f64(), *w))
+            .collect();
+        let loss = -math::slope_xy(&points);
+        (loss > rate_threshold).then_some(loss)
+    }
+    /// Smallest change the current noise lets the scale reliably detect, taken as
+    /// `RESOLUTION_SIGMAS` standard deviations of the full buffer.
+    pub fn effective_resolution(&self) -> Option<f64> {
+        self.buffer_stats()
+            .map(|stats| stats.std_dev * RESOLUTION_SIGMAS)
+    }
+    pub fn flow_rate(&self) -> Option<f64> {
+        if !self.is_buffer_full() {
+            return None;
+        }
+        self.flow_rate_partial()
+    }
+    /// Weight change per second over whatever is buffered (at least two samples),
+    /// so the trend is less trustworthy until the buffer fills.
+    pub fn flow_rate_partial(&self) -> Option<f64> {
+        let per_sample = math::slope(&self.weight_buffer)?;
+        let period = self.sample_period().as_secs_f64();
+        if period > 0. {
+            Some(per_sample / period)
+        } else {
+            None
+        }
+    }
+    /// Projects the buffered depletion trend down to `empty_threshold`; `None` unless depleting,
+    /// and always `None` for a bipolar cell, which has no empty level.
+    pub fn time_to_empty(&self) -> Option<Duration> {
+        if self.settings.bipolar {
+            return None;
+        }
+        let rate = self.flow_rate()?;
+        if !rate.is_finite() || rate >= 0. {
+            return None;
+        }
+        let remaining = (self.weight_buffer.last()? - self.settings.empty_threshold).max(0.);
+        Some(Duration::from_secs_f64(remaining / -rate))
+    }
+    pub fn estimate_time_to_stable(&self) -> Option<Duration> {
+        if !self.is_buffer_full() || self.is_stable() {
+            return None;
+        }
+        let half = self.weight_buffer.len() / 2;
+        if half < 2 {
+            return None;
+        }
+        let (older, newer) = self.weight_buffer.split_at(self.weight_buffer.len() - half);
+        let older_spread = peak_to_peak(&older[older.len() - half..]);
+        let newer_spread = peak_to_peak(newer);
+        let decay_per_sample = (older_spread - newer_spread) / half as f64;
+        if !decay_per_sample.is_finite() || decay_per_sample <= 0. {
+            return None;
+        }
+        let quieting_samples = ((newer_spread - self.config.max_noise) / decay_per_sample).max(0.);
+        let flushing_samples = (self.buffer_length() - half) as f64;
+        Some(
+            self.sample_period()
+                .mul_f64((quieting_samples + flushing_samples).ceil()),
+        )
     }
     pub fn get_weight(&mut self) -> Result<Weight, Error> {
-        let reading = self.get_reading()?;
-        self.update_buffer(reading);
-        if self.is_stable() {
+        if self.paused {
+            return Err(Error::Paused);
+        }
+        let latest_raw = Cell::new(None);
+        let reading = average_of_samples(
+            || {
+                let raw = self.get_raw_reading()?;
+                latest_raw.set(Some(raw.to_bits()));
+                Ok(self.weigh_raw(raw))
+            },
+            self.settings.oversample,
+            self.sample_period(),
+        )?;
+        let latest_raw = latest_raw.get();
+        let duplicate = latest_raw.is_some() && latest_raw == self.last_raw;
+        let reading = if duplicate && self.settings.skip_duplicate_reads {
+            reading
+        } else {
+            let reading = self.filter.apply(reading, self.sample_period());
+            self.update_buffer(reading);
+            self.last_raw = latest_raw;
+            reading
+        };
+        self.check_capacity(reading);
+        let stable = self.is_stable();
+        if stable
+            && !self.was_stable
+            && let Some(on_stable) = self.on_stable.as_mut()
+        {
+            on_stable(reading);
+        }
+        self.was_stable = stable;
+        if stable {
+            self.check_alarms(reading);
+        }
+        self.update_idle(reading, stable)?;
+        if stable {
             Ok(Weight::Stable(reading))
         } else {
             Ok(Weight::Unstable(reading))
         }
     }
+    fn update_idle(&mut self, reading: f64, stable: bool) -> Result<(), Error> {
+        let Some(idle_after) = self.settings.idle_after else {
+            return Ok(());
+        };
+        if let Some(level) = self.idling {
+            if (reading - level).abs() > self.config.max_noise {
+                self.leave_idle()?;
+            }
+        } else if stable {
+            let since = *self.stable_since.get_or_insert_with(Instant::now);
+            if since.elapsed() >= idle_after {
+                let level = self.stable_level().unwrap_or(reading);
+                self.source.set_sample_period(self.settings.idle_period)?;
+                self.idling = Some(level);
+            }
+        } else {
+            self.stable_since = None;
+        }
+        Ok(())
+    }
+    fn leave_idle(&mut self) -> Result<(), Error> {
+        if self.idling.take().is_some() {
+            self.source
+                .set_sample_period(self.config.phidget_sample_period)?;
+            self.stable_since = None;
+        }
+        Ok(())
+    }
+    /// The period samples are arriving at: `settings.idle_period` while idle, otherwise
+    /// the configured one.
+    pub fn sample_period(&self) -> Duration {
+        if self.idling.is_some() {
+            self.settings.idle_period
+        } else {
+            self.config.phidget_sample_period
+        }
+    }
+    pub fn is_idle(&self) -> bool {
+        self.idling.is_some()
+    }
+    pub fn get_timed_weight(&mut self) -> Result<TimedWeight, Error> {
+        let weight = self.get_weight()?;
+        Ok(TimedWeight {
+            weight,
+            at: Instant::now(),
+            timestamp: SystemTime::now(),
+        })
+    }
+    /// Weight rounded to `display_increment`, held until the reading moves more than half
+    /// an increment away from it so jitter doesn't flicker the last digit.
+    pub fn display_value(&mut self) -> Result<f64, Error> {
+        let weight = self.get_weight()?.get_amount();
+        let increment = self.settings.display_increment;
+        let displayed = match self.displayed {
+            Some(held) if (weight - held).abs() <= increment / 2. => held,
+            _ => round_to_increment(weight, increment),
+        };
+        self.displayed = Some(displayed);
+        Ok(displayed)
+    }
+    /// Stores a container weight reported as tare by [`Scale::weights_gnt`]; unlike
+    /// zeroing, the offset is left alone so gross stays visible.
+    pub fn set_tare_weight(&mut self, weight: f64) {
+        self.tare_weight = weight;
+    }
+    /// (gross, net, tare)
+    pub fn weights_gnt(&mut self) -> Result<(f64, f64, f64), Error> {
+        let gross = self.get_weight()?.get_amount();
+        Ok((gross, gross - self.tare_weight, self.tare_weight))
+    }
+    pub fn on_stable_once(&mut self, cb: impl FnMut(f64) + Send + 'static) {
+        self.on_stable = Some(Box::new(cb));
+    }
+    /// Calls the alarm's callback each time the stable weight crosses its level in its
+    /// direction. It re-arms once the weight is stable on the other side again.
+    pub fn add_alarm(&mut self, alarm: AlarmSpec) {
+        self.alarms.push((alarm, false));
+    }
+    fn check_alarms(&mut self, weight: f64) {
+        for (alarm, armed) in &mut self.alarms {
+            let past = match alarm.direction {
+                CrossDir::Rising => weight >= alarm.level,
+                CrossDir::Falling => weight <= alarm.level,
+            };
+            if !past {
+                *armed = true;
+            } else if *armed {
+                *armed = false;
+                (alarm.cb)(weight);
+            }
+        }
+    }
+    /// Go/no-go check: whether the weight is stable and within `tolerance` of `reference`.
+    pub fn matches(&mut self, reference: f64, tolerance: f64) -> Result<bool, Error> {
+        match self.get_weight()? {
+            Weight::Stable(weight) => Ok((weight - reference).abs() <= tolerance),
+            Weight::Unstable(_) => Ok(false),
+        }
+    }
+    pub fn is_present(&mut self, presence_threshold: f64) -> Result<bool, Error> {
+        let weight = self.get_weight()?;
+        Ok(self.is_present_weight(&weight, presence_threshold))
+    }
+    fn is_present_weight(&self, weight: &Weight, presence_threshold: f64) -> bool {
+        weight.is_stable() && self.magnitude(weight.get_amount()) > presence_threshold
+    }
+    fn magnitude(&self, weight: f64) -> f64 {
+        if self.settings.bipolar {
+            weight.abs()
+        } else {
+            weight
+        }
+    }
+    fn check_capacity(&mut self, reading: f64) {
+        let Some(max_rated_weight) = self.settings.max_rated_weight else {
+            return;
+        };
+        let warn_level = max_rated_weight * self.settings.warn_capacity_fraction;
+        if self.magnitude(reading) < warn_level {
+            self.capacity_warned = false;
+        } else if !self.capacity_warned {
+            self.capacity_warned = true;
+            warn!(
+                device:% = self.device, reading;
+                "Scale {} reading {reading} is nearing its rated capacity of {max_rated_weight}",
+                self.device
+            );
+        }
+    }
+    /// Whether a single fresh reading is past `max_rated_weight`, skipping the buffer, the
+    /// read cache and stability so an interlock can act on it immediately.
+    pub fn is_overloaded(&self) -> Result<bool, Error> {
+        let Some(max_rated_weight) = self.settings.max_rated_weight else {
+            return Ok(false);
+        };
+        Ok(self.magnitude(self.get_reading()?) > max_rated_weight)
+    }
+    fn is_clear_weight(&self, weight: &Weight) -> bool {
+        weight.is_stable() && weight.get_amount().abs() < self.config.max_noise
+    }
+    pub fn wait_for_presence(&mut self, threshold: f64, timeout: Duration) -> Result<f64, Error> {
+        let sample_period = self.sample_period();
+        wait_for(
+            || {
+                let weight = self.get_weight()?;
+                Ok(self
+                    .is_present_weight(&weight, threshold)
+                    .then(|| weight.get_amount()))
+            },
+            timeout,
+            sample_period,
+        )
+    }
+    pub fn wait_for_removal(&mut self, timeout: Duration) -> Result<(), Error> {
+        let sample_period = self.sample_period();
+        wait_for(
+            || {
+                let weight = self.get_weight()?;
+                Ok(self.is_clear_weight(&weight).then_some(()))
+            },
+            timeout,
+            sample_period,
+        )
+    }
     pub fn check_for_action(&mut self) -> Option<(Action, f64)> {
         if self.is_stable() {
-            let last = self.weight_buffer.last().unwrap();
+            let level = self.stable_level()?;
             if let Some(last_stable) = self.last_stable_weight {
-                let delta = last - last_stable;
-                if delta.abs() > self.config.max_noise {
-                    info!("Scale: {}; Delta: {delta}", self.get_device());
-                    self.last_stable_weight = Some(*last);
+                if let Some(delta) = math::action_delta(
+                    level,
+                    last_stable,
+                    self.config.max_noise,
+                    self.settings.bipolar,
+                ) {
+                    let confirmations = match self.pending_action {
+                        Some((pending, count))
+                            if (level - pending).abs() < self.config.max_noise =>
+                        {
+                            count + 1
+                        }
+                        _ => 1,
+                    };
+                    if confirmations < self.settings.action_confirm_samples {
+                        self.pending_action = Some((level, confirmations));
+                        return None;
+                    }
+                    self.pending_action = None;
+                    self.last_stable_weight = Some(level);
                     let action = {
                         if delta > 0. {
                             Action::Refilled
@@ -155,44 +1028,181 @@ impl Scale {
                             Action::Served
                         }
                     };
+                    let delta = match self.settings.action_increment {
+                        Some(increment) => round_to_increment(delta, increment),
+                        None => delta,
+                    };
+                    if let Action::Served = action {
+                        self.last_served = Some(-delta);
+                    }
+                    if self.settings.log_actions && delta.abs() >= self.settings.log_min_delta {
+                        log!(
+                            self.settings.action_log_level,
+                            device:% = self.device, action:?, delta;
+                            "Scale: {}; Delta: {delta}", self.device
+                        );
+                    }
                     return Some((action, delta));
                 }
+            } else if self.settings.emit_starting {
+                self.last_stable_weight = Some(level);
+                return Some((Action::Starting, level));
             }
-            self.last_stable_weight = Some(*last);
+            self.pending_action = None;
+            self.last_stable_weight = Some(level);
         }
         None
     }
+    /// Like [`Scale::check_for_action`], but says when there isn't enough data yet to judge
+    /// stability, so callers can show a warm-up state instead of silence.
+    pub fn check_for_action_ready(&mut self) -> Result<Option<(Action, f64)>, NotReady> {
+        let (buffered, needed) = match self.settings.stability_mode {
+            StabilityMode::DualEma { .. } => (usize::from(self.ema.is_some()), 1),
+            StabilityMode::PeakToPeak => (self.weight_buffer.len(), self.stability_window()),
+        };
+        if buffered < needed {
+            return Err(NotReady { buffered, needed });
+        }
+        Ok(self.check_for_action())
+    }
+    /// [`Scale::check_for_action`] with the delta converted to grams for telemetry.
+    pub fn check_for_action_event(&mut self) -> Option<ActionEvent> {
+        self.check_for_action().map(|(action, delta)| ActionEvent {
+            device: self.device.clone(),
+            action,
+            delta: self.settings.weight_unit.to_grams(delta),
+            unit: WeightUnit::Grams,
+        })
+    }
+    /// Compares the amount of the last `Served` action with what an external meter says was
+    /// dispensed. With nothing served yet the scale's side counts as zero.
+    pub fn reconcile(&mut self, external_dispensed: f64, tolerance: f64) -> ReconcileResult {
+        let scale_delta = self.last_served.unwrap_or(0.);
+        ReconcileResult {
+            scale_delta,
+            external: external_dispensed,
+            agree: (scale_delta - external_dispensed).abs() <= tolerance,
+        }
+    }
     pub fn get_config(&self) -> Config {
         self.config.clone()
     }
-    pub fn disconnect(mut self) -> Result<(), Error> {
-        self.vin.close()?;
+    /// A new gain counts as a new calibration; a new offset alone clears the verification,
+    /// as [`Scale::set_offset`] does.
+    pub fn update_config(&mut self, new: Config) -> Result<(), Error> {
+        self.settings.validate(&new)?;
+        if new.phidget_sample_period != self.config.phidget_sample_period {
+            check_sample_period(new.phidget_sample_period, self.supported_sample_interval()?)?;
+        }
+        self.leave_idle()?;
+        self.source.reconfigure(&self.config, &new)?;
+        if new.gain != self.config.gain {
+            self.mark_calibrated();
+        } else if new.offset != self.config.offset {
+            self.verified_with = None;
+        }
+        if new.buffer_length != self.config.buffer_length {
+            self.weight_buffer = Vec::with_capacity(new.buffer_length);
+            self.was_stable = false;
+            self.ema = None;
+            self.pending_action = None;
+            self.filter.reset();
+        }
+        self.config = new;
         Ok(())
     }
+    /// The live config plus runtime state, for attaching to support requests. The phidget
+    /// server password is never serialized.
+    pub fn config_json(&self) -> Result<String, Error> {
+        let bundle = serde_json::json!({
+            "device": self.device.to_string(),
+            "config": self.config,
+            "settings": self.settings,
+            "runtime": {
+                "filter": self.get_filter(),
+                "bias": self.bias,
+                "tare_weight": self.tare_weight,
+                "verified_with": self.verified_with,
+                "last_stable_weight": self.last_stable_weight,
+                "buffered_samples": self.weight_buffer.len(),
+            },
+        });
+        Ok(serde_json::to_string_pretty(&bundle)?)
+    }
+    pub fn get_settings(&self) -> Settings {
+        self.settings.clone()
+    }
+    /// Metrics keep full precision; `display_precision` only applies to
+    /// [`Scale::format_weight`].
+    pub fn prometheus_metrics(&mut self) -> Result<String, Error> {
+        let weight = self.get_weight()?;
+        Ok(self.format_metrics(&weight))
+    }
+    fn format_metrics(&self, weight: &Weight) -> String {
+        use std::fmt::Write;
+        let unit = self.settings.weight_unit;
+        let device = self
+            .device
+            .to_string()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        let noise = if self.weight_buffer.is_empty() {
+            0.
+        } else {
+            peak_to_peak(&self.weight_buffer)
+        };
+        let mut out = String::with_capacity(160);
+        let _ = writeln!(
+            out,
+            "scale_weight_grams{{device=\"{device}\"}} {}",
+            unit.to_grams(weight.get_amount())
+        );
+        let _ = writeln!(
+            out,
+            "scale_stable{{device=\"{device}\"}} {}",
+            u8::from(weight.is_stable())
+        );
+        let _ = writeln!(
+            out,
+            "scale_noise_grams{{device=\"{device}\"}} {}",
+            unit.to_grams(noise)
+        );
+        out
+    }
+    pub fn format_weight(&self, weight: &Weight) -> String {
+        let grams = self.settings.weight_unit.to_grams(weight.get_amount());
+        let weight = match weight {
+            Weight::Stable(_) => Weight::Stable(grams),
+            Weight::Unstable(_) => Weight::Unstable(grams),
+        };
+        format!("{weight:.*}", self.settings.display_precision)
+    }
     pub fn raw_read_once_settled(
         &self,
         stable_samples: usize,
         timeout: Duration,
         max_noise_ratio: f64,
     ) -> Result<f64, Error> {
-        let start_time = std::time::Instant::now();
-        let mut stable_count = 0;
-        let mut starting_reading = self.get_raw_reading()?;
-        while stable_count < stable_samples {
-            let curr_reading = self.get_raw_reading()?;
-            let max_noise = (max_noise_ratio * starting_reading).abs();
-            if (curr_reading - starting_reading).abs() < max_noise {
-                stable_count += 1;
-            } else {
-                stable_count = 0;
-                starting_reading = curr_reading;
-            }
-            sleep(self.config.phidget_sample_period);
-            if start_time.elapsed() > timeout {
-                return Err(Error::Timeout);
-            }
-        }
-        Ok(starting_reading)
+        self.read_once_settled_with_samples(stable_samples, timeout, max_noise_ratio)
+            .map(|(reading, _)| reading)
+    }
+    pub fn read_once_settled_with_samples(
+        &self,
+        stable_samples: usize,
+        timeout: Duration,
+        max_noise_ratio: f64,
+    ) -> Result<(f64, Vec<f64>), Error> {
+        let settle = match self.settings.settle_mode {
+            SettleMode::ResetOnOutlier => read_once_settled,
+            SettleMode::SlidingWindow => read_once_settled_windowed,
+        };
+        settle(
+            || self.settle_raw_reading(),
+            stable_samples,
+            timeout,
+            max_noise_ratio,
+            self.sample_period(),
+        )
     }
     pub fn weigh_once_settled(
         &self,
@@ -200,43 +1210,3129 @@ impl Scale {
         timeout: Duration,
         max_noise_ratio: f64,
     ) -> Result<f64, Error> {
+        let empty = settled_empty(
+            || Ok(self.corrected(self.get_raw_reading()?)),
+            stable_samples.min(EMPTY_FAST_PATH_SAMPLES),
+            self.config.max_noise,
+            self.sample_period(),
+        )?;
+        if let Some(weight) = empty {
+            return Ok(weight);
+        }
         self.raw_read_once_settled(stable_samples, timeout, max_noise_ratio)
-            .map(|r| r * self.config.gain - self.config.offset)
+            .map(|r| self.corrected(r))
     }
-    pub fn set_calibration(&mut self, empty_reading: f64, weight_reading: f64, weight: f64) {
-        self.config.gain = weight / (weight_reading - empty_reading);
-        self.config.offset = weight * empty_reading / (weight_reading - empty_reading);
+    /// Settles the last `stable_samples` weights to within `coarse_noise`, then keeps the
+    /// same window sliding until it's within `fine_noise`. A window that already meets the
+    /// fine tolerance returns without further reads.
+    pub fn weigh_two_stage(
+        &self,
+        coarse_noise: f64,
+        fine_noise: f64,
+        stable_samples: usize,
+        timeout: Duration,
+    ) -> Result<f64, Error> {
+        let start = Instant::now();
+        let mut window = Vec::with_capacity(stable_samples.max(1));
+        for max_noise in [coarse_noise.max(fine_noise), fine_noise] {
+            settle_window(
+                || self.get_reading(),
+                &mut window,
+                stable_samples,
+                max_noise,
+                timeout.saturating_sub(start.elapsed()),
+                self.sample_period(),
+            )?;
+        }
+        math::mean(&window).ok_or(Error::Timeout)
     }
-}
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use menu::device::Model;
-    fn make_scale() -> Result<Scale, Error> {
-        let empty_reading = -0.000003141351044178009;
-        let weight_reading = 0.0001232493668794632;
-        let test_weight = 1277.;
+    /// Settles like the weight buffer does, but on `deadline` returns the latest window
+    /// average flagged as unsettled instead of timing out.
+    pub fn weigh_best_effort(&self, deadline: Duration) -> Result<BestEffortReading, Error> {
+        best_effort(
+            || self.get_reading(),
+            self.config.buffer_length,
+            self.config.max_noise,
+            deadline,
+            self.sample_period(),
+        )
+    }
+    pub fn weigh_with_retry(
+        &self,
+        attempts: usize,
+        stable_samples: usize,
+        timeout: Duration,
+        max_noise_ratio: f64,
+    ) -> Result<f64, Error> {
+        retry(attempts, || {
+            self.weigh_once_settled(stable_samples, timeout, max_noise_ratio)
+        })
+    }
+    pub fn average_over(&self, window: Duration) -> Result<f64, Error> {
+        average_over_window(|| self.get_reading(), window, self.sample_period())
+    }
+    /// Mean of `samples` readings and the half-width of its two-sided `confidence` interval.
+    pub fn weigh_with_confidence(
+        &self,
+        samples: usize,
+        confidence: f64,
+        timeout: Duration,
+    ) -> Result<(f64, f64), Error> {
+        if samples < 2 || !(confidence > 0. && confidence < 1.) {
+            return Err(Error::OutOfRange);
+        }
+        let start_time = std::time::Instant::now();
+        let mut readings = Vec::with_capacity(samples);
+        while readings.len() < samples {
+            readings.push(self.get_reading()?);
+            if readings.len() < samples {
+                if start_time.elapsed() > timeout {
+                    return Err(Error::Timeout);
+                }
+                sleep(self.sample_period());
+            }
+        }
+        Ok(confidence_interval(&readings, confidence))
+    }
+    pub fn weigh_once_adaptive(
+        &self,
+        target_precision: f64,
+        timeout: Duration,
+    ) -> Result<f64, Error> {
+        mean_to_precision(
+            || self.get_reading(),
+            target_precision,
+            timeout,
+            self.sample_period(),
+        )
+        .map(|(mean, _)| mean)
+    }
+    pub fn set_calibration(
+        &mut self,
+        empty_reading: f64,
+        weight_reading: f64,
+        weight: f64,
+    ) -> Result<(), Error> {
+        if weight_reading == empty_reading {
+            return Err(Error::InvalidConfig(
+                "empty and loaded readings are equal".into(),
+            ));
+        }
+        let gain = weight / (weight_reading - empty_reading);
+        self.check_gain(gain)?;
+        self.config.gain = gain;
+        self.config.offset = weight * empty_reading / (weight_reading - empty_reading);
+        self.mark_calibrated();
+        Ok(())
+    }
+    /// Calibrates from raw samples a caller captured itself, dropping outliers from each set
+    /// before averaging. Returns the applied `(gain, offset)`.
+    pub fn calibrate_from_samples(
+        &mut self,
+        empty: &[f64],
+        loaded: &[f64],
+        known_weight: f64,
+    ) -> Result<(f64, f64), Error> {
+        let (Some(empty_reading), Some(weight_reading)) =
+            (outlier_free_mean(empty), outlier_free_mean(loaded))
+        else {
+            return Err(Error::InvalidConfig(
+                "calibration needs empty and loaded samples".into(),
+            ));
+        };
+        self.set_calibration(empty_reading, weight_reading, known_weight)?;
+        Ok((self.config.gain, self.config.offset))
+    }
+    pub fn set_gain(&mut self, gain: f64) -> Result<(), Error> {
+        self.check_gain(gain)?;
+        self.config.gain = gain;
+        self.mark_calibrated();
+        Ok(())
+    }
+    pub fn set_offset(&mut self, offset: f64) {
+        self.config.offset = offset;
+        self.verified_with = None;
+    }
+    /// Calibrates a count-based ADC such as an HX711: `weight = (raw - zero_count) / counts_per_gram`.
+    pub fn set_counts_calibration(
+        &mut self,
+        counts_per_gram: f64,
+        zero_count: f64,
+    ) -> Result<(), Error> {
+        if !counts_per_gram.is_finite() || counts_per_gram == 0. || !zero_count.is_finite() {
+            return Err(Error::InvalidConfig(format!(
+                "counts_per_gram {counts_per_gram} with zero {zero_count} isn't a usable calibration"
+            )));
+        }
+        let gain = 1. / counts_per_gram;
+        self.check_gain(gain)?;
+        self.config.gain = gain;
+        self.config.offset = zero_count / counts_per_gram;
+        self.mark_calibrated();
+        Ok(())
+    }
+    pub fn counts_per_gram(&self) -> f64 {
+        1. / self.config.gain
+    }
+    pub fn zero_count(&self) -> f64 {
+        self.config.offset / self.config.gain
+    }
+    fn settled_raw_average(&self, samples: usize, timeout: Duration) -> Result<f64, Error> {
+        average_once_settled(
+            || self.settle_raw_reading(),
+            samples,
+            self.config.max_noise / self.config.gain.abs(),
+            timeout,
+            self.sample_period(),
+        )
+    }
+    pub fn tare_averaged(&mut self, samples: usize, timeout: Duration) -> Result<(), Error> {
+        let empty_reading = self.settled_raw_average(samples, timeout)?;
+        self.set_tare_offset(self.config.offset + self.zeroed(empty_reading));
+        Ok(())
+    }
+    /// Tares only if the settled weight is below `presence_threshold`; returns whether it did.
+    pub fn tare_if_empty(
+        &mut self,
+        presence_threshold: f64,
+        samples: usize,
+        timeout: Duration,
+    ) -> Result<bool, Error> {
+        let reading = self.settled_raw_average(samples, timeout)?;
+        let weight = self.corrected(reading);
+        if weight.abs() >= presence_threshold {
+            warn!(
+                "{}: not taring with {weight:.2} {} on the pan",
+                self.device, self.settings.weight_unit
+            );
+            return Ok(false);
+        }
+        self.set_tare_offset(self.config.offset + self.zeroed(reading));
+        Ok(true)
+    }
+    /// Measures how far a settled `known_weight` reads from its true value, relative to the
+    /// empty pan, over `cycles` load/unload cycles and subtracts the mean from subsequent
+    /// readings. `prompt` is
+    /// called before each step and should return once the pan is ready.
+    pub fn learn_bias(
+        &mut self,
+        known_weight: f64,
+        cycles: usize,
+        samples: usize,
+        timeout: Duration,
+        mut prompt: impl FnMut(LoadPrompt),
+    ) -> Result<f64, Error> {
+        let cycles = cycles.max(1);
+        let mut total = 0.;
+        for _ in 0..cycles {
+            prompt(LoadPrompt::Remove);
+            let empty = self.transformed(self.settled_raw_average(samples, timeout)?);
+            prompt(LoadPrompt::Place);
+            let loaded = self.transformed(self.settled_raw_average(samples, timeout)?);
+            total += loaded - empty - known_weight;
+        }
+        self.bias = total / cycles as f64;
+        info!(
+            "{}: learned bias of {:.3} {}",
+            self.device, self.bias, self.settings.weight_unit
+        );
+        Ok(self.bias)
+    }
+    pub fn recalibrate_span(
+        &mut self,
+        known_weight: f64,
+        samples: usize,
+        timeout: Duration,
+    ) -> Result<f64, Error> {
+        let reference_reading = self.settled_raw_average(samples, timeout)?;
+        self.apply_span(reference_reading, known_weight)
+    }
+    fn apply_span(&mut self, reference_reading: f64, known_weight: f64) -> Result<f64, Error> {
+        let gain = (known_weight + self.config.offset) / reference_reading;
+        if !gain.is_finite() {
+            return Err(Error::InvalidConfig(
+                "reference reading can't produce a finite gain".into(),
+            ));
+        }
+        self.check_gain(gain)?;
+        self.config.gain = gain;
+        self.mark_calibrated();
+        Ok(gain)
+    }
+    fn check_gain(&self, gain: f64) -> Result<(), Error> {
+        check_gain(
+            gain,
+            self.settings.weight_unit,
+            self.source.plausible_gain(),
+        )
+    }
+    pub fn verify_calibration(
+        &mut self,
+        known_weight: f64,
+        tolerance: f64,
+        samples: usize,
+        timeout: Duration,
+    ) -> Result<bool, Error> {
+        let reading = self.settled_raw_average(samples, timeout)?;
+        let weight = self.raw_to_weight(reading);
+        let passed = (weight - known_weight).abs() <= tolerance;
+        if passed {
+            self.verified_with = Some(known_weight);
+        }
+        Ok(passed)
+    }
+    /// Two-point calibration that only sticks if re-weighing `known_weight` lands within
+    /// `tolerance`; otherwise the previous gain and offset are restored.
+    pub fn calibrate_and_verify(
+        &mut self,
+        known_weight: f64,
+        tolerance: f64,
+        samples: usize,
+        timeout: Duration,
+        mut prompt: impl FnMut(LoadPrompt),
+    ) -> Result<(), Error> {
+        prompt(LoadPrompt::Remove);
+        let empty_reading = self.settled_raw_average(samples, timeout)?;
+        prompt(LoadPrompt::Place);
+        let weight_reading = self.settled_raw_average(samples, timeout)?;
+        let previous = (
+            self.config.gain,
+            self.config.offset,
+            self.verified_with,
+            self.settings.calibrated_at,
+        );
+        #[cfg(feature = "temperature")]
+        let previous_temp_calibration = self.temp_calibration;
+        self.set_calibration(empty_reading, weight_reading, known_weight)?;
+        let verified = self.verify_calibration(known_weight, tolerance, samples, timeout);
+        if let Ok(true) = verified {
+            return Ok(());
+        }
+        (
+            self.config.gain,
+            self.config.offset,
+            self.verified_with,
+            self.settings.calibrated_at,
+        ) = previous;
+        #[cfg(feature = "temperature")]
+        {
+            self.temp_calibration = previous_temp_calibration;
+        }
+        verified?;
+        Err(Error::InvalidConfig(format!(
+            "new calibration didn't reproduce {known_weight} within {tolerance}; rolled back"
+        )))
+    }
+    fn mark_calibrated(&mut self) {
+        #[cfg(feature = "temperature")]
+        if self.temp_calibration.take().is_some() {
+            info!(
+                "{}: new calibration replaces the temperature calibration",
+                self.device
+            );
+        }
+        self.verified_with = None;
+        self.settings.calibrated_at = Some(SystemTime::now());
+    }
+    pub fn calibration_age(&self) -> Option<Duration> {
+        let calibrated_at = self.settings.calibrated_at?;
+        Some(
+            SystemTime::now()
+                .duration_since(calibrated_at)
+                .unwrap_or_default(),
+        )
+    }
+    /// Whether the last calibration is older than `interval`, or unknown; warns if so.
+    pub fn needs_recalibration(&self, interval: Duration) -> bool {
+        match self.calibration_age() {
+            Some(age) if age <= interval => false,
+            Some(age) => {
+                warn!(
+                    "{}: calibration is {} days old, past the {} day interval",
+                    self.device,
+                    age.as_secs() / 86_400,
+                    interval.as_secs() / 86_400
+                );
+                true
+            }
+            None => {
+                warn!("{}: no calibration date on record", self.device);
+                true
+            }
+        }
+    }
+    pub fn calibration_report(&self) -> CalibrationReport {
+        CalibrationReport {
+            device: self.get_device(),
+            gain: self.config.gain,
+            offset: self.config.offset,
+            timestamp: SystemTime::now(),
+            calibrated_at: self.settings.calibrated_at,
+            verified_with: self.verified_with,
+        }
+    }
+}
+const RESOLUTION_SIGMAS: f64 = 3.;
+/// Samples further than this many median absolute deviations from the median are outliers.
+const MAD_OUTLIER_SCALE: f64 = 4.;
+fn outlier_free_mean(samples: &[f64]) -> Option<f64> {
+    let mut sorted = samples.to_vec();
+    let center = math::median(&mut sorted)?;
+    let mut deviations: Vec<f64> = samples.iter().map(|s| (s - center).abs()).collect();
+    let limit = math::median(&mut deviations)? * MAD_OUTLIER_SCALE;
+    let kept: Vec<f64> = samples
+        .iter()
+        .copied()
+        .filter(|s| (s - center).abs() <= limit)
+        .collect();
+    math::mean(&kept)
+}
+/// Two full swings.
+const MIN_OSCILLATION_CROSSINGS: usize = 4;
+fn check_gain(
+    gain: f64,
+    unit: WeightUnit,
+    plausible_grams: std::ops::RangeInclusive<f64>,
+) -> Result<(), Error> {
+    if !gain.is_finite() {
+        return Err(Error::InvalidConfig(format!("gain {gain} is not finite")));
+    }
+    if gain <= 0. {
+        return Err(Error::InvalidConfig(format!(
+            "gain {gain} is not positive; are the empty and loaded readings swapped?"
+        )));
+    }
+    if !plausible_grams.contains(&unit.to_grams(gain)) {
+        return Err(Error::InvalidConfig(format!(
+            "gain {gain} {unit} per raw unit is implausible for this source; check the known weight"
+        )));
+    }
+    Ok(())
+}
+fn round_to_increment(value: f64, increment: f64) -> f64 {
+    if increment > 0. {
+        (value / increment).round() * increment
+    } else {
+        value
+    }
+}
+fn check_sample_period(period: Duration, (min, max): (Duration, Duration)) -> Result<(), Error> {
+    if period < min || period > max {
+        return Err(Error::OutOfRange);
+    }
+    Ok(())
+}
+fn average_of_samples(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    samples: usize,
+    sample_period: Duration,
+) -> Result<f64, Error> {
+    let samples = samples.max(1);
+    let mut sum = read()?;
+    for _ in 1..samples {
+        sleep(sample_period);
+        sum += read()?;
+    }
+    Ok(sum / samples as f64)
+}
+fn best_effort(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    window_length: usize,
+    max_noise: f64,
+    deadline: Duration,
+    sample_period: Duration,
+) -> Result<BestEffortReading, Error> {
+    let start_time = std::time::Instant::now();
+    let window_length = window_length.max(1);
+    let mut window = Vec::with_capacity(window_length);
+    let mut samples_taken = 0;
+    loop {
+        if window.len() == window_length {
+            window.remove(0);
+        }
+        window.push(read()?);
+        samples_taken += 1;
+        let settled = window.len() == window_length && peak_to_peak(&window) < max_noise;
+        if settled || start_time.elapsed() > deadline {
+            return Ok(BestEffortReading {
+                value: window.iter().sum::<f64>() / window.len() as f64,
+                settled,
+                samples_taken,
+            });
+        }
+        sleep(sample_period);
+    }
+}
+fn retry<T>(attempts: usize, mut f: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e @ (Error::Phidget(_) | Error::Timeout)) if attempt < attempts => {
+                warn!("Attempt {attempt}/{attempts} failed, retrying: {e}");
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+const EMPTY_FAST_PATH_SAMPLES: usize = 3;
+/// The mean of `samples` readings if every one is within `max_noise` of zero. An empty pan
+/// settles almost instantly, so there's no point waiting out the full settle.
+fn settled_empty(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    samples: usize,
+    max_noise: f64,
+    sample_period: Duration,
+) -> Result<Option<f64>, Error> {
+    if samples == 0 {
+        return Ok(None);
+    }
+    let mut sum = 0.;
+    for i in 0..samples {
+        if i > 0 {
+            sleep(sample_period);
+        }
+        let weight = read()?;
+        if weight.abs() >= max_noise {
+            return Ok(None);
+        }
+        sum += weight;
+    }
+    Ok(Some(sum / samples as f64))
+}
+fn read_once_settled(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    stable_samples: usize,
+    timeout: Duration,
+    max_noise_ratio: f64,
+    sample_period: Duration,
+) -> Result<(f64, Vec<f64>), Error> {
+    let start_time = std::time::Instant::now();
+    let mut samples = Vec::with_capacity(stable_samples);
+    let mut starting_reading = read()?;
+    while samples.len() < stable_samples {
+        let curr_reading = read()?;
+        let max_noise = (max_noise_ratio * starting_reading).abs();
+        if (curr_reading - starting_reading).abs() < max_noise {
+            samples.push(curr_reading);
+        } else {
+            samples.clear();
+            starting_reading = curr_reading;
+        }
+        sleep(sample_period);
+        if start_time.elapsed() > timeout {
+            return Err(Error::Timeout);
+        }
+    }
+    Ok((starting_reading, samples))
+}
+/// Slides `window` over new readings until its `stable_samples` are within `max_noise`.
+fn settle_window(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    window: &mut Vec<f64>,
+    stable_samples: usize,
+    max_noise: f64,
+    timeout: Duration,
+    sample_period: Duration,
+) -> Result<(), Error> {
+    let start_time = Instant::now();
+    let stable_samples = stable_samples.max(1);
+    loop {
+        if window.len() == stable_samples && peak_to_peak(window) < max_noise {
+            return Ok(());
+        }
+        if start_time.elapsed() > timeout {
+            return Err(Error::Timeout);
+        }
+        if window.len() == stable_samples {
+            window.remove(0);
+        }
+        window.push(read()?);
+        if window.len() < stable_samples || peak_to_peak(window) >= max_noise {
+            sleep(sample_period);
+        }
+    }
+}
+fn read_once_settled_windowed(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    stable_samples: usize,
+    timeout: Duration,
+    max_noise_ratio: f64,
+    sample_period: Duration,
+) -> Result<(f64, Vec<f64>), Error> {
+    let start_time = std::time::Instant::now();
+    let stable_samples = stable_samples.max(1);
+    let mut window = Vec::with_capacity(stable_samples);
+    loop {
+        if window.len() == stable_samples {
+            window.remove(0);
+        }
+        window.push(read()?);
+        if window.len() == stable_samples {
+            let mean = window.iter().sum::<f64>() / stable_samples as f64;
+            if peak_to_peak(&window) < (max_noise_ratio * mean).abs() {
+                return Ok((mean, window));
+            }
+        }
+        sleep(sample_period);
+        if start_time.elapsed() > timeout {
+            return Err(Error::Timeout);
+        }
+    }
+}
+fn mean_to_precision(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    target_precision: f64,
+    timeout: Duration,
+    sample_period: Duration,
+) -> Result<(f64, usize), Error> {
+    let start_time = std::time::Instant::now();
+    let mut count = 0;
+    let mut mean = 0.;
+    let mut sum_squares = 0.;
+    loop {
+        let reading = read()?;
+        count += 1;
+        let delta = reading - mean;
+        mean += delta / count as f64;
+        sum_squares += delta * (reading - mean);
+        if count >= 3 {
+            let variance = sum_squares / (count - 1) as f64;
+            let standard_error = (variance / count as f64).sqrt();
+            if standard_error < target_precision {
+                return Ok((mean, count));
+            }
+        }
+        if start_time.elapsed() > timeout {
+            return Err(Error::Timeout);
+        }
+        sleep(sample_period);
+    }
+}
+fn confidence_interval(samples: &[f64], confidence: f64) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.);
+    let t = student_t_quantile(1. - (1. - confidence) / 2., n - 1.);
+    (mean, t * (variance / n).sqrt())
+}
+/// Student's t quantile, found by bisecting the exact CDF so small samples get their
+/// full width.
+fn student_t_quantile(p: f64, dof: f64) -> f64 {
+    if p < 0.5 {
+        return -student_t_quantile(1. - p, dof);
+    }
+    let mut high = 1.;
+    while student_t_cdf(high, dof) < p && high < 1e12 {
+        high *= 2.;
+    }
+    let mut low = 0.;
+    for _ in 0..200 {
+        let mid = (low + high) / 2.;
+        if student_t_cdf(mid, dof) < p {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    (low + high) / 2.
+}
+fn student_t_cdf(t: f64, dof: f64) -> f64 {
+    let tail = 0.5 * regularized_beta(dof / 2., 0.5, dof / (dof + t * t));
+    if t >= 0. { 1. - tail } else { tail }
+}
+/// I_x(a, b) by continued fraction (Numerical Recipes, `betai`).
+fn regularized_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0. {
+        return 0.;
+    }
+    if x >= 1. {
+        return 1.;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1. - x).ln()).exp();
+    if x < (a + 1.) / (a + b + 2.) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1. - front * beta_fraction(b, a, 1. - x) / b
+    }
+}
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.;
+    let mut d = 1. - (a + b) * x / (a + 1.);
+    d = 1. / if d.abs() < TINY { TINY } else { d };
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        for numerator in [
+            m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.)),
+        ] {
+            d = 1. + numerator * d;
+            d = 1. / if d.abs() < TINY { TINY } else { d };
+            c = 1. + numerator / c;
+            c = if c.abs() < TINY { TINY } else { c };
+            h *= d * c;
+        }
+        if (d * c - 1.).abs() < 1e-15 {
+            break;
+        }
+    }
+    h
+}
+/// Lanczos approximation (g = 7).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1. - x);
+    }
+    let x = x - 1.;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.));
+    0.5 * (2. * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+fn wait_for<T>(
+    mut poll: impl FnMut() -> Result<Option<T>, Error>,
+    timeout: Duration,
+    sample_period: Duration,
+) -> Result<T, Error> {
+    let start_time = std::time::Instant::now();
+    loop {
+        if let Some(value) = poll()? {
+            return Ok(value);
+        }
+        if start_time.elapsed() > timeout {
+            return Err(Error::Timeout);
+        }
+        sleep(sample_period);
+    }
+}
+fn average_over_window(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    window: Duration,
+    sample_period: Duration,
+) -> Result<f64, Error> {
+    let start_time = std::time::Instant::now();
+    let mut sum = 0.;
+    let mut count = 0;
+    loop {
+        sum += read()?;
+        count += 1;
+        if start_time.elapsed() >= window {
+            return Ok(sum / count as f64);
+        }
+        sleep(sample_period);
+    }
+}
+fn average_once_settled(
+    mut read: impl FnMut() -> Result<f64, Error>,
+    samples: usize,
+    max_spread: f64,
+    timeout: Duration,
+    sample_period: Duration,
+) -> Result<f64, Error> {
+    let start_time = std::time::Instant::now();
+    let samples = samples.max(1);
+    let mut window: Vec<f64> = Vec::with_capacity(samples);
+    loop {
+        if window.len() == samples {
+            window.remove(0);
+        }
+        window.push(read()?);
+        if window.len() == samples && peak_to_peak(&window) < max_spread {
+            return Ok(window.iter().sum::<f64>() / samples as f64);
+        }
+        if start_time.elapsed() > timeout {
+            return Err(Error::Timeout);
+        }
+        sleep(sample_period);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::{convert_raw, convert_raw_in_place};
+    use crate::settings::ServerAddr;
+    use crate::source::ScriptedSource;
+    use menu::device::Model;
+    fn make_scale() -> Result<Scale, Error> {
+        let empty_reading = -0.000003141351044178009;
+        let weight_reading = 0.0001232493668794632;
+        let test_weight = 1277.;
+
+        let config = Config {
+            phidget_id: 716588,
+            load_cell_id: 0,
+            gain: test_weight / (weight_reading - empty_reading),
+            offset: test_weight * empty_reading / (weight_reading - empty_reading),
+            ..Default::default()
+        };
+
+        let mut scale =
+            DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0")).connect()?;
+        scale.set_calibration(empty_reading, weight_reading, test_weight)?;
+        Ok(scale)
+    }
+    fn unopened_scale(config: Config, settings: Settings) -> Scale {
+        DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .with_settings(settings)
+            .connect_unopened()
+            .unwrap()
+    }
+    fn scripted_scale(
+        config: Config,
+        settings: Settings,
+        readings: impl IntoIterator<Item = f64>,
+    ) -> Scale<ScriptedSource> {
+        DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .with_settings(settings)
+            .connect_with_source(ScriptedSource::new(readings))
+            .unwrap()
+    }
+    fn scripted_config(buffer_length: usize, max_noise: f64) -> Config {
+        Config {
+            gain: 1.,
+            offset: 0.,
+            buffer_length,
+            max_noise,
+            ..Default::default()
+        }
+    }
+    fn settle_at(scale: &mut Scale, weight: f64) -> Option<(Action, f64)> {
+        for _ in 0..scale.config.buffer_length {
+            scale.update_buffer(weight);
+        }
+        scale.check_for_action()
+    }
+    mod capture {
+        use log::{Log, Metadata, Record};
+        use std::sync::{Mutex, Once};
+
+        pub struct Captured {
+            pub level: log::Level,
+            pub device: Option<String>,
+            pub message: String,
+        }
+        static RECORDS: Mutex<Vec<Captured>> = Mutex::new(Vec::new());
+        static INIT: Once = Once::new();
+        struct CaptureLogger;
+        impl Log for CaptureLogger {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &Record) {
+                RECORDS.lock().unwrap().push(Captured {
+                    level: record.level(),
+                    device: record
+                        .key_values()
+                        .get("device".into())
+                        .map(|d| d.to_string()),
+                    message: record.args().to_string(),
+                });
+            }
+            fn flush(&self) {}
+        }
+        pub fn init() {
+            INIT.call_once(|| {
+                log::set_logger(&CaptureLogger).unwrap();
+                log::set_max_level(log::LevelFilter::Trace);
+            });
+        }
+        pub fn for_device(device: &str) -> Vec<Captured> {
+            RECORDS
+                .lock()
+                .unwrap()
+                .drain(..)
+                .filter(|r| r.device.as_deref() == Some(device))
+                .collect()
+        }
+    }
+    #[test]
+    #[ignore = "needs phidget hardware"]
+    fn weigh_once_settled() -> Result<(), Error> {
+        let scale = make_scale()?;
+        let weight = scale.weigh_once_settled(3, Duration::from_secs(10), 0.1)?;
+        println!("DEBUG: {weight}");
+        Ok(())
+    }
+    #[test]
+    #[ignore = "needs phidget hardware"]
+    fn supported_sample_interval() -> Result<(), Error> {
+        let scale = make_scale()?;
+        let (min, max) = scale.supported_sample_interval()?;
+        assert!(min <= max);
+        Ok(())
+    }
+    #[test]
+    #[ignore = "needs phidget hardware"]
+    fn phidget_info() -> Result<(), Error> {
+        let scale = make_scale()?;
+        let info = scale.phidget_info()?;
+        assert_eq!(info.serial, 716588);
+        assert_eq!(info.channel, 0);
+        assert!(info.attached);
+        Ok(())
+    }
+    #[test]
+    #[ignore = "needs phidget hardware"]
+    fn remote_phidget_connects() -> Result<(), Error> {
+        let config = Config {
+            phidget_id: 716588,
+            load_cell_id: 0,
+            ..Default::default()
+        };
+        let settings = Settings {
+            phidget_server: Some(ServerAddr::new("localhost", 5661)),
+            ..Default::default()
+        };
+        let scale = DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .with_settings(settings)
+            .connect()?;
+        assert!(scale.is_connected());
+        Ok(())
+    }
+    #[test]
+    fn phidget_server_carried_to_scale() {
+        let settings = Settings {
+            phidget_server: Some(ServerAddr::new("192.168.1.20", 5661)),
+            ..Default::default()
+        };
+        let scale = unopened_scale(Config::default(), settings);
+        let server = scale.get_settings().phidget_server.unwrap();
+        assert_eq!(server.address, "192.168.1.20");
+        assert_eq!(server.port, 5661);
+        assert_eq!(server.name, "192.168.1.20");
+        assert!(server.password.is_empty());
+    }
+    #[test]
+    fn sliding_window_settles_through_periodic_outliers() {
+        let script = [110., 100., 100.2, 99.9];
+        let cycling = |script: [f64; 4]| {
+            let mut i = 0;
+            move || {
+                i += 1;
+                Ok(script[(i - 1) % script.len()])
+            }
+        };
+        let timeout = Duration::from_millis(50);
+        assert!(matches!(
+            read_once_settled(cycling(script), 3, timeout, 0.01, Duration::ZERO),
+            Err(Error::Timeout)
+        ));
+        let (reading, window) =
+            read_once_settled_windowed(cycling(script), 3, timeout, 0.01, Duration::ZERO).unwrap();
+        assert_eq!(window, vec![100., 100.2, 99.9]);
+        assert!((reading - 100.033).abs() < 1e-3);
+    }
+    #[test]
+    fn sample_period_limits_come_from_source() -> Result<(), Error> {
+        struct FixedRate;
+        impl RawSource for FixedRate {
+            fn read(&self) -> Result<f64, Error> {
+                Ok(0.)
+            }
+            fn min_sample_period(&self) -> Result<Duration, Error> {
+                Ok(Duration::from_millis(100))
+            }
+            fn max_sample_period(&self) -> Result<Duration, Error> {
+                Ok(Duration::from_millis(100))
+            }
+        }
+        let mut scripted = scripted_scale(scripted_config(1, 1.), Settings::default(), [0.]);
+        assert_eq!(
+            scripted.supported_sample_interval()?,
+            (Duration::ZERO, Duration::MAX)
+        );
+        scripted.set_sample_period(Duration::from_millis(7))?;
+        assert_eq!(
+            scripted.get_config().phidget_sample_period,
+            Duration::from_millis(7)
+        );
+        let mut fixed =
+            DisconnectedScale::new(scripted_config(1, 1.), Device::new(Model::LibraV0, "L0"))
+                .connect_with_source(FixedRate)?;
+        assert!(matches!(
+            fixed.set_sample_period(Duration::from_millis(7)),
+            Err(Error::OutOfRange)
+        ));
+        fixed.set_sample_period(Duration::from_millis(100))?;
+        Ok(())
+    }
+    #[test]
+    fn empty_pan_settles_fast() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(5),
+            ..scripted_config(1, 1.)
+        };
+        let empty = scripted_scale(config.clone(), Settings::default(), [0.2, -0.1, 0.]);
+        let start = Instant::now();
+        assert!(
+            empty
+                .weigh_once_settled(20, Duration::from_secs(5), 0.01)?
+                .abs()
+                < 1.
+        );
+        let empty_elapsed = start.elapsed();
+        let loaded = scripted_scale(config, Settings::default(), [0.3, 60., 95., 100.]);
+        let start = Instant::now();
+        assert_eq!(
+            loaded.weigh_once_settled(20, Duration::from_secs(5), 0.01)?,
+            100.
+        );
+        assert!(empty_elapsed * 3 < start.elapsed());
+        Ok(())
+    }
+    #[test]
+    fn confidence_interval_narrows_with_samples() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let scale = DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .connect_with_source(ScriptedSource::cycling([99., 101.]))?;
+        let timeout = Duration::from_secs(1);
+        let (mean, few) = scale.weigh_with_confidence(4, 0.95, timeout)?;
+        assert_eq!(mean, 100.);
+        let (_, many) = scale.weigh_with_confidence(32, 0.95, timeout)?;
+        assert!(many < few);
+        let (_, looser) = scale.weigh_with_confidence(32, 0.99, timeout)?;
+        assert!(looser > many);
+        assert!(matches!(
+            scale.weigh_with_confidence(1, 0.95, timeout),
+            Err(Error::OutOfRange)
+        ));
+        Ok(())
+    }
+    #[test]
+    fn student_t_quantile_matches_tables() {
+        assert!((student_t_quantile(0.975, 1.) - 12.706).abs() < 1e-3);
+        assert!((student_t_quantile(0.975, 2.) - 4.303).abs() < 1e-3);
+        assert!((student_t_quantile(0.995, 3.) - 5.841).abs() < 1e-3);
+        assert!((student_t_quantile(0.975, 10.) - 2.228).abs() < 1e-3);
+        assert!((student_t_quantile(0.025, 30.) + 2.042).abs() < 1e-3);
+        assert!((student_t_quantile(0.975, 1e9) - 1.960).abs() < 1e-3);
+    }
+    #[cfg(feature = "temperature")]
+    #[test]
+    #[ignore = "needs phidget hardware"]
+    fn temperature_reads() -> Result<(), Error> {
+        let mut scale = make_scale()?;
+        scale.attach_temperature(0)?;
+        let temperature = scale.temperature()?;
+        assert!((-40. ..85.).contains(&temperature));
+        Ok(())
+    }
+    #[cfg(feature = "temperature")]
+    #[test]
+    fn tare_shifts_temperature_calibration() -> Result<(), Error> {
+        use crate::calibration::Calibration;
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), [3e-4]);
+        scale.scripted_temperature = Some(20.);
+        scale.set_temp_calibration(Some(TempCalibration {
+            cold: (
+                0.,
+                Calibration {
+                    gain: 1e6,
+                    offset: 10.,
+                },
+            ),
+            hot: (
+                40.,
+                Calibration {
+                    gain: 1.2e6,
+                    offset: 30.,
+                },
+            ),
+        }));
+        // 1.1e6 * 3e-4 - 20 at the 20 C midpoint.
+        assert!((scale.get_instant_weight()? - 310.).abs() < 1e-9);
+        scale.tare_averaged(3, Duration::from_secs(1))?;
+        assert!(scale.get_instant_weight()?.abs() < 1e-9);
+        scale.scripted_temperature = Some(40.);
+        // The tare holds as a shift of the interpolated zero: 1.2e6 * 3e-4 - 30 - 310.
+        assert!((scale.get_instant_weight()? - 20.).abs() < 1e-9);
+        scale.set_calibration(1e-4, 2e-4, 1000.)?;
+        assert!(scale.temp_calibration.is_none());
+        Ok(())
+    }
+    #[cfg(feature = "temperature")]
+    #[test]
+    fn thermal_coefficient_cancels_zero_drift() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        // The zero climbs 0.05 g/C between 20 C and 30 C.
+        let mut scale = scripted_scale(config, Settings::default(), [100., 100.5]);
+        assert!(matches!(
+            scale.set_thermal_coefficient(Some(0.05)),
+            Err(Error::NoTemperature)
+        ));
+        scale.scripted_temperature = Some(20.);
+        scale.set_thermal_coefficient(Some(0.05))?;
+        assert!((scale.get_instant_weight()? - 100.).abs() < 1e-9);
+        scale.scripted_temperature = Some(30.);
+        assert!((scale.get_instant_weight()? - 100.).abs() < 1e-9);
+        scale.tare_averaged(3, Duration::from_secs(1))?;
+        assert!(scale.get_instant_weight()?.abs() < 1e-9);
+        scale.set_thermal_coefficient(None)?;
+        assert!((scale.get_instant_weight()? - 0.5).abs() < 1e-9);
+        Ok(())
+    }
+    #[cfg(feature = "temperature")]
+    #[test]
+    fn temperature_is_opt_in() {
+        assert_eq!(Settings::default().temperature_channel, None);
+        let scale = scripted_scale(scripted_config(1, 1.), Settings::default(), [0.]);
+        assert!(matches!(scale.temperature(), Err(Error::NoTemperature)));
+    }
+    #[test]
+    fn best_effort_returns_unsettled_average() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(2, 1.)
+        };
+        let scale = DisconnectedScale::new(config.clone(), Device::new(Model::LibraV0, "L0"))
+            .connect_with_source(ScriptedSource::cycling([0., 10.]))?;
+        let reading = scale.weigh_best_effort(Duration::from_millis(20))?;
+        assert!(!reading.settled);
+        assert_eq!(reading.value, 5.);
+        assert!(reading.samples_taken > 2);
+
+        let scale = DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .connect_with_source(ScriptedSource::new([50., 7., 7.2]))?;
+        let reading = scale.weigh_best_effort(Duration::from_secs(1))?;
+        assert_eq!(
+            reading,
+            BestEffortReading {
+                value: 7.1,
+                settled: true,
+                samples_taken: 3,
+            }
+        );
+        Ok(())
+    }
+    #[test]
+    fn tare_if_empty_zeroes_an_empty_pan() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), [0.2]);
+        assert!(scale.tare_if_empty(5., 3, Duration::from_secs(1))?);
+        assert!((scale.get_config().offset - 0.2).abs() < 1e-12);
+        Ok(())
+    }
+    #[test]
+    fn tare_ramp_blends_to_new_zero() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let settings = Settings {
+            tare_ramp: Some(Duration::from_millis(500)),
+            ..Settings::default()
+        };
+        let mut scale = scripted_scale(config, settings, [12.]);
+        scale.tare_averaged(3, Duration::from_secs(1))?;
+        // Checks only the shape, not the timing: the reading starts between the two zeros
+        // and falls monotonically to the new one.
+        let mut previous = scale.get_instant_weight()?;
+        assert!(previous > 0. && previous <= 12.);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while previous > 0. {
+            assert!(Instant::now() < deadline, "ramp never reached the new zero");
+            sleep(Duration::from_millis(20));
+            let weight = scale.get_instant_weight()?;
+            assert!(weight <= previous);
+            previous = weight;
+        }
+        assert_eq!(previous, 0.);
+        Ok(())
+    }
+    #[test]
+    fn filter_applies_in_get_weight() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings::default(),
+            [10., 10., 90., 10.],
+        );
+        scale.set_filter(Filter::Median(3));
+        assert_eq!(scale.get_filter(), Filter::Median(3));
+        let weights: Vec<f64> = (0..4)
+            .map(|_| scale.get_weight().map(|w| w.get_amount()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(weights, vec![10., 10., 10., 10.]);
+        Ok(())
+    }
+    #[test]
+    fn overload_at_and_above_rating() -> Result<(), Error> {
+        let settings = Settings {
+            max_rated_weight: Some(5_000.),
+            ..Settings::default()
+        };
+        let scale = scripted_scale(scripted_config(10, 1.), settings, [4_999., 5_000., 5_000.1]);
+        assert!(!scale.is_overloaded()?);
+        assert!(!scale.is_overloaded()?);
+        assert!(scale.is_overloaded()?);
+        assert!(scale.weight_buffer.is_empty());
+        let unrated = scripted_scale(scripted_config(10, 1.), Settings::default(), [1e9]);
+        assert!(!unrated.is_overloaded()?);
+        Ok(())
+    }
+    #[test]
+    fn effective_resolution_is_three_sigma() {
+        let mut scale = unopened_scale(scripted_config(4, 1.), Settings::default());
+        for weight in [99., 101., 99.] {
+            scale.update_buffer(weight);
+            assert_eq!(scale.effective_resolution(), None);
+        }
+        scale.update_buffer(101.);
+        // Sample std-dev of ±1 around 100 over four samples is sqrt(4/3).
+        let resolution = scale.effective_resolution().unwrap();
+        assert!((resolution - 3. * (4f64 / 3.).sqrt()).abs() < 1e-9);
+    }
+    #[test]
+    fn two_stage_is_no_slower_than_fine_settle() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(5),
+            ..scripted_config(1, 1.)
+        };
+        let script = [130., 104., 101., 100.4, 99.7, 100.1, 100.2, 100., 100.1];
+        let timeout = Duration::from_secs(5);
+        let single = scripted_scale(config.clone(), Settings::default(), script);
+        let fine = single.weigh_two_stage(0.5, 0.5, 3, timeout)?;
+        let two_stage = scripted_scale(config.clone(), Settings::default(), script);
+        assert_eq!(two_stage.weigh_two_stage(10., 0.5, 3, timeout)?, fine);
+        assert!(two_stage.source.reads() <= single.source.reads());
+        assert!((fine - 100.1).abs() < 1e-9);
+        let quiet = scripted_scale(config, Settings::default(), [50.]);
+        assert_eq!(quiet.weigh_two_stage(10., 0.5, 3, timeout)?, 50.);
+        assert!(quiet.source.reads() < single.source.reads());
+        Ok(())
+    }
+    #[test]
+    fn read_into_fills_caller_buffer() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let scale = scripted_scale(config.clone(), Settings::default(), [1., 2., 3.]);
+        let mut out = [0.; 5];
+        assert_eq!(scale.read_into(&mut out)?, 5);
+        assert_eq!(out, [1., 2., 3., 3., 3.]);
+        assert_eq!(scale.read_into(&mut [])?, 0);
+        let failing = scripted_scale(config, Settings::default(), []);
+        assert!(matches!(failing.read_into(&mut out), Err(Error::Timeout)));
+        Ok(())
+    }
+    #[test]
+    fn oscillation_versus_drift() {
+        let mut springy = unopened_scale(scripted_config(24, 1.), Settings::default());
+        for i in 0..24 {
+            springy.update_buffer(100. + 5. * (i as f64 * std::f64::consts::PI / 4.).sin());
+        }
+        assert!(springy.is_oscillating());
+        let mut drifting = unopened_scale(scripted_config(24, 1.), Settings::default());
+        for i in 0..24 {
+            drifting.update_buffer(100. + i as f64);
+        }
+        assert!(!drifting.is_oscillating());
+    }
+    #[test]
+    fn calibrate_from_noisy_samples() -> Result<(), Error> {
+        let mut scale = unopened_scale(scripted_config(1, 1.), Settings::default());
+        let empty = [1.0e-4, 1.02e-4, 0.98e-4, 9e-4, 1.01e-4, 0.99e-4];
+        let loaded = [2.0e-4, 2.01e-4, 1.99e-4, 2.02e-4, 1.98e-4, -5e-4];
+        let (gain, offset) = scale.calibrate_from_samples(&empty, &loaded, 1000.)?;
+        assert!((gain - 1e7).abs() < 1e3);
+        assert!((offset - 1000.).abs() < 0.1);
+        assert!((scale.raw_to_weight(1.5e-4) - 500.).abs() < 0.1);
+        assert!(matches!(
+            scale.calibrate_from_samples(&[], &loaded, 1000.),
+            Err(Error::InvalidConfig(_))
+        ));
+        Ok(())
+    }
+    #[test]
+    fn hx711_style_gain_is_plausible_for_its_source() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        assert!(matches!(
+            unopened_scale(config.clone(), Settings::default()).set_gain(1. / 420.),
+            Err(Error::InvalidConfig(_))
+        ));
+        let mut scale = DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .connect_with_source(
+                ScriptedSource::new([8_420_000.]).with_plausible_gain(1e-4..=1.),
+            )?;
+        scale.set_gain(1. / 420.)?;
+        scale.set_calibration(8_000_000., 8_420_000., 1000.)?;
+        assert!((scale.config.gain - 1. / 420.).abs() < 1e-9);
+        let (gain, _) = scale.calibrate_from_samples(&[8_000_000.; 4], &[8_420_000.; 4], 1000.)?;
+        assert!((gain - 1. / 420.).abs() < 1e-9);
+        let gain = scale.recalibrate_span(1000., 4, Duration::from_secs(1))?;
+        assert!((gain - 1. / 420.).abs() < 1e-9);
+        assert!(matches!(scale.set_gain(1e7), Err(Error::InvalidConfig(_))));
+        Ok(())
+    }
+    #[test]
+    fn settled_weights_apply_transform() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let mut scale = scripted_scale(config.clone(), Settings::default(), [100.]);
+        scale.set_transform(Box::new(|w| w * 2.));
+        assert_eq!(scale.get_instant_weight()?, 200.);
+        assert_eq!(
+            scale.weigh_once_settled(3, Duration::from_secs(1), 0.01)?,
+            200.
+        );
+        let mut small = scripted_scale(config, Settings::default(), [3.]);
+        small.set_transform(Box::new(|w| w * 2.));
+        assert!(!small.tare_if_empty(5., 3, Duration::from_secs(1))?);
+        Ok(())
+    }
+    #[test]
+    fn tare_if_empty_refuses_a_loaded_pan() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), [500.]);
+        assert!(!scale.tare_if_empty(5., 3, Duration::from_secs(1))?);
+        assert_eq!(scale.get_config().offset, 0.);
+        Ok(())
+    }
+    #[test]
+    fn connect_wait_retries_until_device_appears() -> Result<(), Error> {
+        let configured =
+            DisconnectedScale::new(Config::default(), Device::new(Model::LibraV0, "L0"));
+        let mut attempts = 0;
+        configured.clone().connect_wait_with(
+            Duration::from_secs(1),
+            Duration::from_millis(1),
+            |scale| {
+                attempts += 1;
+                if attempts < 3 {
+                    Err(Error::Phidget(phidget::ReturnCode::Timeout))
+                } else {
+                    scale.connect_unopened()
+                }
+            },
+        )?;
+        assert_eq!(attempts, 3);
+
+        let result = configured.connect_wait_with(
+            Duration::from_millis(20),
+            Duration::from_millis(5),
+            |_| Err(Error::Initialization),
+        );
+        assert!(matches!(result, Err(Error::Initialization)));
+        Ok(())
+    }
+    #[test]
+    fn try_connect_outcomes() {
+        let configured = DisconnectedScale::new(
+            Config {
+                phidget_id: 716588,
+                ..Default::default()
+            },
+            Device::new(Model::LibraV0, "L0"),
+        );
+        assert!(matches!(
+            configured.clone().try_connect_among(&[716589]),
+            ConnectOutcome::Absent(_)
+        ));
+        assert!(matches!(
+            configured
+                .clone()
+                .try_connect_with(&[716588], DisconnectedScale::connect_unopened),
+            ConnectOutcome::Connected(_)
+        ));
+        match configured.try_connect_with(&[716588], |_| Err(Error::Initialization)) {
+            ConnectOutcome::Failed(scale, Error::Initialization) => {
+                assert_eq!(
+                    scale.get_device().to_string(),
+                    Device::new(Model::LibraV0, "L0").to_string()
+                );
+            }
+            _ => panic!("expected a failed outcome"),
+        }
+    }
+    #[test]
+    fn settles_on_any_raw_source() -> Result<(), Error> {
+        let config = Config {
+            gain: 2.,
+            offset: 1.,
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let scale = scripted_scale(config, Settings::default(), [40., 10., 10.05, 10.02, 9.98]);
+        let weight = scale.weigh_once_settled(3, Duration::from_secs(1), 0.01)?;
+        assert_eq!(weight, 19.);
+        Ok(())
+    }
+    #[test]
+    fn learned_bias_is_removed() -> Result<(), Error> {
+        struct Shared(std::rc::Rc<Cell<f64>>);
+        impl RawSource for Shared {
+            fn read(&self) -> Result<f64, Error> {
+                Ok(self.0.get())
+            }
+        }
+        let pan = std::rc::Rc::new(Cell::new(0.));
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let mut scale = DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .connect_with_source(Shared(pan.clone()))?;
+        let mut prompts = Vec::new();
+        let bias = scale.learn_bias(100., 2, 3, Duration::from_secs(1), |prompt| {
+            prompts.push(prompt);
+            // The empty pan reads 2 g, which the bias mustn't absorb.
+            pan.set(match prompt {
+                LoadPrompt::Place => 102.5,
+                LoadPrompt::Remove => 2.,
+            });
+        })?;
+        assert_eq!(bias, 0.5);
+        assert_eq!(
+            prompts,
+            vec![
+                LoadPrompt::Remove,
+                LoadPrompt::Place,
+                LoadPrompt::Remove,
+                LoadPrompt::Place
+            ]
+        );
+        assert_eq!(scale.get_weight()?.get_amount(), 102.);
+        Ok(())
+    }
+    #[test]
+    fn sample_period_validation() {
+        let bounds = (Duration::from_millis(8), Duration::from_secs(60));
+        assert!(check_sample_period(Duration::from_millis(8), bounds).is_ok());
+        assert!(check_sample_period(Duration::from_secs(1), bounds).is_ok());
+        assert!(matches!(
+            check_sample_period(Duration::from_millis(1), bounds),
+            Err(Error::OutOfRange)
+        ));
+        assert!(matches!(
+            check_sample_period(Duration::from_secs(61), bounds),
+            Err(Error::OutOfRange)
+        ));
+    }
+    #[test]
+    fn buffer_rolls_over_scripted_readings() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings::default(),
+            [1., 2., 3., 4., 5.],
+        );
+        for _ in 0..5 {
+            scale.get_weight()?;
+        }
+        assert_eq!(scale.weight_buffer, vec![3., 4., 5.]);
+        Ok(())
+    }
+    #[test]
+    fn timed_weights_are_ordered() -> Result<(), Error> {
+        let mut scale = scripted_scale(scripted_config(3, 1.), Settings::default(), [4., 5.]);
+        let first = scale.get_timed_weight()?;
+        sleep(Duration::from_millis(2));
+        let second = scale.get_timed_weight()?;
+        assert_eq!(first.weight.get_amount(), 4.);
+        assert_eq!(second.weight.get_amount(), 5.);
+        assert!(second.at > first.at);
+        assert!(second.timestamp > first.timestamp);
+        assert!(second.epoch_millis() > first.epoch_millis());
+        Ok(())
+    }
+    #[test]
+    fn transform_applies_after_gain_and_offset() -> Result<(), Error> {
+        let config = Config {
+            gain: 2.,
+            offset: 1.,
+            ..scripted_config(3, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), [5.]);
+        assert_eq!(scale.get_weight()?.get_amount(), 9.);
+        scale.set_transform(Box::new(|weight| weight * 2.));
+        assert_eq!(scale.get_weight()?.get_amount(), 18.);
+        assert_eq!(scale.get_instant_weight()?, 18.);
+        Ok(())
+    }
+    #[test]
+    fn display_value_holds_through_jitter() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings::default(),
+            [10.2, 10.4, 9.7, 10.45, 10.6, 12.3],
+        );
+        let shown: Vec<f64> = (0..6)
+            .map(|_| scale.display_value())
+            .collect::<Result<_, _>>()?;
+        assert_eq!(shown, vec![10., 10., 10., 10., 11., 12.]);
+        Ok(())
+    }
+    #[test]
+    fn gross_net_tare() -> Result<(), Error> {
+        let mut scale = scripted_scale(scripted_config(3, 1.), Settings::default(), [250., 730.]);
+        assert_eq!(scale.weights_gnt()?, (250., 250., 0.));
+        scale.set_tare_weight(250.);
+        assert_eq!(scale.weights_gnt()?, (730., 480., 250.));
+        assert_eq!(scale.get_config().offset, 0.);
+        Ok(())
+    }
+    #[test]
+    fn settle_state_follows_buffer_trend() {
+        let state = |samples: &[f64]| {
+            let mut scale = unopened_scale(scripted_config(4, 1.), Settings::default());
+            for &sample in samples {
+                scale.update_buffer(sample);
+            }
+            scale.settle_state()
+        };
+        assert_eq!(
+            state(&[10., 20., 30., 40.]),
+            SettleState {
+                stable: false,
+                direction: Direction::Rising
+            }
+        );
+        assert_eq!(
+            state(&[40., 30., 20., 10.]),
+            SettleState {
+                stable: false,
+                direction: Direction::Falling
+            }
+        );
+        assert_eq!(
+            state(&[10., 10.2, 9.9, 10.1]),
+            SettleState {
+                stable: true,
+                direction: Direction::Flat
+            }
+        );
+        assert_eq!(state(&[]).direction, Direction::Flat);
+    }
+    #[test]
+    fn mean_baseline_steadies_deltas() {
+        let cycles = [
+            [0.4, -0.4, 0.4, -0.4],
+            [99.6, 100.4, 99.6, 100.4],
+            [-0.4, 0.4, -0.4, 0.4],
+            [100.4, 99.6, 100.4, 99.6],
+        ];
+        let mut scale = unopened_scale(scripted_config(4, 1.), Settings::default());
+        let mut deltas = Vec::new();
+        for cycle in cycles {
+            for sample in cycle {
+                scale.update_buffer(sample);
+            }
+            deltas.extend(scale.check_for_action().map(|(_, delta)| delta.abs()));
+        }
+        let single_sample: Vec<f64> = cycles
+            .windows(2)
+            .map(|pair| (pair[1][3] - pair[0][3]).abs())
+            .collect();
+        assert_eq!(deltas.len(), 3);
+        assert!(peak_to_peak(&deltas) < 1e-9);
+        assert!(peak_to_peak(&single_sample) > 1.);
+    }
+    #[test]
+    fn recorder_logs_each_reading() -> Result<(), Error> {
+        let path = std::env::temp_dir().join(format!("scale-recorder-{}.csv", std::process::id()));
+        let config = Config {
+            gain: 2.,
+            ..scripted_config(3, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), [1., 2., 3.]);
+        scale.enable_recorder(&path, 4096)?;
+        for _ in 0..3 {
+            scale.get_weight()?;
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+        let rows: Vec<&str> = contents
+            .lines()
+            .map(|line| line.split_once(',').unwrap().1)
+            .collect();
+        assert_eq!(rows, vec!["1,2", "2,4", "3,6"]);
+        Ok(())
+    }
+    #[test]
+    fn duplicate_reads_do_not_fill_the_window() -> Result<(), Error> {
+        let settings = Settings {
+            skip_duplicate_reads: true,
+            ..Default::default()
+        };
+        let mut scale = scripted_scale(scripted_config(3, 1.), settings.clone(), [10.]);
+        for _ in 0..5 {
+            assert!(scale.get_weight()?.is_unstable());
+        }
+        assert_eq!(scale.buffer_snapshot(), vec![10.]);
+
+        // Reads outside get_weight don't count as buffered.
+        let mut scale = scripted_scale(scripted_config(3, 1.), settings, [10.]);
+        scale.get_instant_weight()?;
+        scale.get_weight()?;
+        assert_eq!(scale.buffer_snapshot(), vec![10.]);
+
+        let mut scale = scripted_scale(scripted_config(3, 1.), Settings::default(), [10.]);
+        for _ in 0..2 {
+            scale.get_weight()?;
+        }
+        assert!(scale.get_weight()?.is_stable());
+        Ok(())
+    }
+    #[test]
+    fn config_json_has_key_fields() -> Result<(), Error> {
+        let config = Config {
+            phidget_id: 716588,
+            gain: 4_500_000.,
+            ..scripted_config(3, 1.)
+        };
+        let settings = Settings {
+            phidget_server: Some(crate::settings::ServerAddr {
+                password: "hunter2".into(),
+                ..crate::settings::ServerAddr::new("10.0.0.2", 5661)
+            }),
+            ..Default::default()
+        };
+        let mut scale = scripted_scale(config, settings, [0.]);
+        scale.set_tare_weight(12.);
+        let json = scale.config_json()?;
+        let parsed: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(parsed["config"]["phidget_id"], 716588);
+        assert_eq!(parsed["config"]["gain"], 4_500_000.);
+        assert_eq!(parsed["config"]["buffer_length"], 3);
+        assert_eq!(parsed["settings"]["phidget_server"]["address"], "10.0.0.2");
+        assert_eq!(parsed["settings"]["phidget_server"]["port"], 5661);
+        assert_eq!(parsed["settings"]["action_log_level"], "INFO");
+        assert_eq!(parsed["settings"]["deglitch_raw"], false);
+        assert_eq!(parsed["runtime"]["filter"], "None");
+        assert_eq!(parsed["runtime"]["tare_weight"], 12.);
+        assert!(!json.contains("hunter2"));
+        Ok(())
+    }
+    #[test]
+    fn idle_slows_sampling_until_motion() -> Result<(), Error> {
+        let settings = Settings {
+            idle_after: Some(Duration::ZERO),
+            idle_period: Duration::from_secs(2),
+            ..Default::default()
+        };
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            settings,
+            [10., 10.2, 9.9, 10.1, 50.],
+        );
+        let active = scale.get_config().phidget_sample_period;
+        for _ in 0..2 {
+            scale.get_weight()?;
+        }
+        assert!(!scale.is_idle());
+        scale.get_weight()?;
+        assert!(scale.is_idle());
+        assert_eq!(scale.sample_period(), Duration::from_secs(2));
+        assert_eq!(scale.source.sample_period(), Some(Duration::from_secs(2)));
+        assert_eq!(scale.get_config().phidget_sample_period, active);
+        let json: serde_json::Value = serde_json::from_str(&scale.config_json()?)?;
+        assert_eq!(
+            json["config"]["phidget_sample_period"],
+            serde_json::to_value(active)?
+        );
+        scale.get_weight()?;
+        assert!(scale.is_idle());
+        scale.get_weight()?;
+        assert!(!scale.is_idle());
+        assert_eq!(scale.sample_period(), active);
+        assert_eq!(scale.source.sample_period(), Some(active));
+        Ok(())
+    }
+    #[test]
+    fn idle_period_must_be_supported() {
+        let settings = Settings {
+            idle_after: Some(Duration::from_secs(5)),
+            idle_period: Duration::from_secs(120),
+            ..Default::default()
+        };
+        let source = ScriptedSource::new([0.])
+            .with_sample_periods(Duration::from_millis(8), Duration::from_secs(60));
+        assert!(matches!(
+            DisconnectedScale::new(scripted_config(3, 1.), Device::new(Model::LibraV0, "L0"))
+                .with_settings(settings)
+                .connect_with_source(source),
+            Err(Error::OutOfRange)
+        ));
+    }
+    #[test]
+    fn buffer_snapshot_is_oldest_first() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(4, 1.),
+            Settings::default(),
+            [1., 2., 3., 4., 5., 6.],
+        );
+        scale.get_weight()?;
+        scale.get_weight()?;
+        assert_eq!(scale.buffer_snapshot(), vec![1., 2.]);
+        for _ in 0..4 {
+            scale.get_weight()?;
+        }
+        assert_eq!(scale.buffer_snapshot(), vec![3., 4., 5., 6.]);
+        Ok(())
+    }
+    #[test]
+    fn scripted_weight_stabilizes_once_buffer_fills() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings::default(),
+            [10., 10.2, 9.9, 10.1, 30.],
+        );
+        assert!(scale.get_weight()?.is_unstable());
+        assert!(scale.get_weight()?.is_unstable());
+        assert!(scale.get_weight()?.is_stable());
+        assert!(scale.get_weight()?.is_stable());
+        assert!(scale.get_weight()?.is_unstable());
+        Ok(())
+    }
+    #[test]
+    fn scripted_actions_from_get_weight() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings::default(),
+            [0., 0., 0., 75., 75., 75., 25., 25., 25.],
+        );
+        let mut actions = Vec::new();
+        for _ in 0..9 {
+            scale.get_weight()?;
+            actions.extend(scale.check_for_action());
+        }
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(actions[0], (Action::Refilled, 75.)));
+        assert!(matches!(actions[1], (Action::Served, -50.)));
+        Ok(())
+    }
+    #[test]
+    fn raw_weight_round_trip() {
+        let config = Config {
+            gain: 10_133_482.,
+            offset: -31.83,
+            ..Default::default()
+        };
+        let scale = unopened_scale(config, Settings::default());
+        for weight in [0., 12.5, 1277., -40.] {
+            let round_trip = scale.raw_to_weight(scale.weight_to_raw(weight));
+            assert!((round_trip - weight).abs() < 1e-9);
+        }
+    }
+    #[test]
+    fn on_stable_fires_once_per_episode() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings::default(),
+            [5., 5., 5., 5., 5., 40., 40., 40., 40.],
+        );
+        let stabilized = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = stabilized.clone();
+        scale.on_stable_once(move |weight| record.lock().unwrap().push(weight));
+        for _ in 0..9 {
+            scale.get_weight()?;
+        }
+        assert_eq!(*stabilized.lock().unwrap(), vec![5., 40.]);
+        Ok(())
+    }
+    #[test]
+    fn low_alarm_fires_once_per_crossing() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(1, 1.),
+            Settings::default(),
+            [50., 300., 250., 90., 60., 150., 80.],
+        );
+        let fired = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let record = fired.clone();
+        scale.add_alarm(AlarmSpec {
+            level: 100.,
+            direction: CrossDir::Falling,
+            cb: Box::new(move |weight| record.lock().unwrap().push(weight)),
+        });
+        for _ in 0..7 {
+            scale.get_weight()?;
+        }
+        assert_eq!(*fired.lock().unwrap(), vec![90., 80.]);
+        Ok(())
+    }
+    #[test]
+    fn matches_within_tolerance() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(2, 1.),
+            Settings::default(),
+            [498., 501., 501.3, 503., 503.2],
+        );
+        assert!(!scale.matches(500., 2.)?);
+        assert!(!scale.matches(500., 2.)?);
+        assert!(scale.matches(500., 2.)?);
+        assert!(!scale.matches(500., 2.)?);
+        assert!(!scale.matches(500., 2.)?);
+        Ok(())
+    }
+    #[test]
+    fn not_ready_until_buffer_fills() {
+        let mut scale = unopened_scale(scripted_config(3, 1.), Settings::default());
+        assert_eq!(
+            scale.check_for_action_ready().unwrap_err(),
+            NotReady {
+                buffered: 0,
+                needed: 3
+            }
+        );
+        scale.update_buffer(10.);
+        scale.update_buffer(10.);
+        assert_eq!(
+            scale.check_for_action_ready().unwrap_err(),
+            NotReady {
+                buffered: 2,
+                needed: 3
+            }
+        );
+        scale.update_buffer(10.);
+        assert!(matches!(scale.check_for_action_ready(), Ok(None)));
+    }
+    #[test]
+    fn deglitched_read_drops_middle_glitch() -> Result<(), Error> {
+        let scale = scripted_scale(
+            scripted_config(1, 1.),
+            Settings::default(),
+            [1.2e-4, 9.9e-3, 1.21e-4, 1.3e-4],
+        );
+        assert_eq!(scale.get_raw_reading_deglitched()?, 1.21e-4);
+        assert_eq!(scale.get_raw_reading()?, 1.3e-4);
+        let settings = Settings {
+            deglitch_raw: true,
+            ..Settings::default()
+        };
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(1, 1.)
+        };
+        let glitchy = |settings| {
+            DisconnectedScale::new(config.clone(), Device::new(Model::LibraV0, "L0"))
+                .with_settings(settings)
+                .connect_with_source(ScriptedSource::cycling([5., 5., 900.]))
+        };
+        let timeout = Duration::from_millis(50);
+        assert_eq!(
+            glitchy(settings)?.raw_read_once_settled(3, timeout, 0.01)?,
+            5.
+        );
+        assert!(matches!(
+            glitchy(Settings::default())?.raw_read_once_settled(3, timeout, 0.01),
+            Err(Error::Timeout)
+        ));
+        Ok(())
+    }
+    #[test]
+    fn instant_reads_cached_within_ttl() -> Result<(), Error> {
+        let scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings {
+                read_cache_ttl: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            [1., 2., 3.],
+        );
+        assert_eq!(scale.get_instant_weight()?, 1.);
+        assert_eq!(scale.get_instant_weight()?, 1.);
+        sleep(Duration::from_millis(60));
+        assert_eq!(scale.get_instant_weight()?, 2.);
+        Ok(())
+    }
+    #[test]
+    fn instant_reads_uncached_by_default() -> Result<(), Error> {
+        let scale = scripted_scale(scripted_config(3, 1.), Settings::default(), [1., 2.]);
+        assert_eq!(scale.get_instant_weight()?, 1.);
+        assert_eq!(scale.get_instant_weight()?, 2.);
+        Ok(())
+    }
+    #[test]
+    fn first_stable_reading_emits_starting() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings {
+                emit_starting: true,
+                ..Default::default()
+            },
+            [20., 20., 20., 20., 60., 60., 60.],
+        );
+        let mut actions = Vec::new();
+        for _ in 0..7 {
+            scale.get_weight()?;
+            actions.extend(scale.check_for_action());
+        }
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(actions[0], (Action::Starting, 20.)));
+        assert!(matches!(actions[1], (Action::Refilled, 40.)));
+        Ok(())
+    }
+    #[test]
+    fn live_config_update_keeps_buffer() -> Result<(), Error> {
+        let mut scale = scripted_scale(scripted_config(3, 1.), Settings::default(), [7.]);
+        for _ in 0..3 {
+            scale.get_weight()?;
+        }
+        scale.update_config(scripted_config(3, 0.5))?;
+        assert_eq!(scale.weight_buffer, vec![7., 7., 7.]);
+        assert_eq!(scale.get_config().max_noise, 0.5);
+        assert!(scale.get_weight()?.is_stable());
+        Ok(())
+    }
+    #[test]
+    fn config_update_checks_period_and_resets_calibration() -> Result<(), Error> {
+        let source = ScriptedSource::new([7.])
+            .with_sample_periods(Duration::from_millis(8), Duration::from_secs(60));
+        let mut scale =
+            DisconnectedScale::new(scripted_config(3, 1.), Device::new(Model::LibraV0, "L0"))
+                .connect_with_source(source)?;
+        let too_fast = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(3, 1.)
+        };
+        assert!(matches!(
+            scale.update_config(too_fast),
+            Err(Error::OutOfRange)
+        ));
+        assert_eq!(
+            scale.get_config().phidget_sample_period,
+            scripted_config(3, 1.).phidget_sample_period
+        );
+
+        scale.verified_with = Some(1000.);
+        scale.update_config(Config {
+            offset: 5.,
+            ..scripted_config(3, 1.)
+        })?;
+        assert_eq!(scale.verified_with, None);
+        assert!(scale.get_settings().calibrated_at.is_none());
+
+        scale.verified_with = Some(1000.);
+        scale.update_config(Config {
+            gain: 2.,
+            ..scripted_config(3, 1.)
+        })?;
+        assert_eq!(scale.verified_with, None);
+        assert!(scale.get_settings().calibrated_at.is_some());
+        Ok(())
+    }
+    #[test]
+    fn buffer_length_update_clears_buffer() -> Result<(), Error> {
+        let mut scale = scripted_scale(scripted_config(3, 1.), Settings::default(), [7.]);
+        for _ in 0..3 {
+            scale.get_weight()?;
+        }
+        scale.update_config(scripted_config(5, 1.))?;
+        assert!(scale.weight_buffer.is_empty());
+        for _ in 0..4 {
+            assert!(scale.get_weight()?.is_unstable());
+        }
+        assert!(scale.get_weight()?.is_stable());
+        Ok(())
+    }
+    #[test]
+    fn dual_ema_buffer_resize_does_not_panic() -> Result<(), Error> {
+        let settings = Settings {
+            stability_mode: StabilityMode::DualEma {
+                fast_alpha: 0.5,
+                slow_alpha: 0.1,
+            },
+            ..Settings::default()
+        };
+        let mut scale = scripted_scale(scripted_config(3, 1.), settings, [7.]);
+        for _ in 0..3 {
+            scale.get_weight()?;
+        }
+        scale.update_config(scripted_config(5, 1.))?;
+        assert!(scale.ema.is_none());
+        assert!(scale.check_for_action().is_none());
+        Ok(())
+    }
+    #[cfg(feature = "find_phidgets")]
+    struct MockUsb {
+        ids: (u16, u16),
+        serial: Result<Option<&'static str>, rusb::Error>,
+    }
+    #[cfg(feature = "find_phidgets")]
+    impl UsbDevice for MockUsb {
+        fn vendor_product(&self) -> Result<(u16, u16), Error> {
+            Ok(self.ids)
+        }
+        fn serial_number(&self) -> Result<Option<String>, Error> {
+            Ok(self.serial.map(|sn| sn.map(String::from))?)
+        }
+    }
+    #[cfg(feature = "find_phidgets")]
+    #[test]
+    fn discovery_skips_devices_that_fail_to_open() {
+        let phidget = (PHIDGET_VENDOR_ID, PHIDGET_PRODUCT_ID);
+        let devices = vec![
+            MockUsb {
+                ids: phidget,
+                serial: Ok(Some("716588")),
+            },
+            MockUsb {
+                ids: phidget,
+                serial: Err(rusb::Error::Busy),
+            },
+            MockUsb {
+                ids: (0x046d, 0xc52b),
+                serial: Ok(Some("1")),
+            },
+            MockUsb {
+                ids: phidget,
+                serial: Ok(Some("716589")),
+            },
+        ];
+        assert_eq!(collect_phidget_ids(devices).unwrap(), vec![716588, 716589]);
+    }
+    #[cfg(feature = "find_phidgets")]
+    #[test]
+    fn discovery_reports_usb_permission_errors() {
+        let devices = vec![MockUsb {
+            ids: (PHIDGET_VENDOR_ID, PHIDGET_PRODUCT_ID),
+            serial: Err(rusb::Error::Access),
+        }];
+        assert!(matches!(
+            collect_phidget_ids(devices),
+            Err(Error::UsbPermission)
+        ));
+    }
+    #[test]
+    fn retry_succeeds_after_transient_errors() {
+        let mut calls = 0;
+        let result = retry(3, || {
+            calls += 1;
+            match calls {
+                1 => Err(Error::Timeout),
+                2 => Err(Error::Phidget(phidget::ReturnCode::Timeout)),
+                _ => Ok(42.),
+            }
+        });
+        assert_eq!(result.unwrap(), 42.);
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: Result<f64, Error> = retry(2, || {
+            calls += 1;
+            Err(Error::Timeout)
+        });
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert_eq!(calls, 2);
+
+        let mut calls = 0;
+        let result: Result<f64, Error> = retry(5, || {
+            calls += 1;
+            Err(Error::OutOfRange)
+        });
+        assert!(matches!(result, Err(Error::OutOfRange)));
+        assert_eq!(calls, 1);
+    }
+    #[test]
+    fn weight_stability_predicates() {
+        let stable = Weight::Stable(12.);
+        assert!(stable.is_stable());
+        assert!(!stable.is_unstable());
+        let unstable = Weight::Unstable(12.);
+        assert!(unstable.is_unstable());
+        assert!(!unstable.is_stable());
+    }
+    #[test]
+    fn averaged_tare_is_closer_to_zero() -> Result<(), Error> {
+        let true_zero = 0.001;
+        let noise = [4e-6, -3e-6, 2e-6, -2.5e-6, 1e-6, -1.5e-6];
+        let mut readings = noise.iter().map(|n| true_zero + n).cycle();
+        let single = average_once_settled(
+            || Ok(readings.next().unwrap()),
+            1,
+            1e-5,
+            Duration::from_secs(1),
+            Duration::ZERO,
+        )?;
+        let mut readings = noise.iter().map(|n| true_zero + n).cycle();
+        let averaged = average_once_settled(
+            || Ok(readings.next().unwrap()),
+            noise.len(),
+            1e-5,
+            Duration::from_secs(1),
+            Duration::ZERO,
+        )?;
+        assert!((averaged - true_zero).abs() < (single - true_zero).abs());
+        Ok(())
+    }
+    #[test]
+    fn averaged_tare_waits_for_stability() {
+        let mut readings = [0.1, 0.5, 0.9, 1.3].into_iter().cycle();
+        let result = average_once_settled(
+            || Ok(readings.next().unwrap()),
+            3,
+            0.01,
+            Duration::from_millis(20),
+            Duration::from_millis(1),
+        );
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+    #[test]
+    fn average_over_short_window() -> Result<(), Error> {
+        let mut calls = 0;
+        let mut readings = [1., 3.].into_iter().cycle();
+        let average = average_over_window(
+            || {
+                calls += 1;
+                Ok(readings.next().unwrap())
+            },
+            Duration::from_millis(30),
+            Duration::from_millis(2),
+        )?;
+        assert!(calls > 4);
+        assert!((average - 2.).abs() <= 1. / calls as f64);
+        Ok(())
+    }
+    #[test]
+    fn presence_at_empty_and_loaded() {
+        let scale = unopened_scale(Config::default(), Settings::default());
+        assert!(!scale.is_present_weight(&Weight::Stable(0.4), 5.));
+        assert!(scale.is_present_weight(&Weight::Stable(250.), 5.));
+        assert!(!scale.is_present_weight(&Weight::Unstable(250.), 5.));
+        assert!(!scale.is_present_weight(&Weight::Stable(-250.), 5.));
+    }
+    #[test]
+    fn wait_for_place_then_remove() -> Result<(), Error> {
+        let scale = unopened_scale(
+            Config {
+                max_noise: 1.,
+                ..Default::default()
+            },
+            Settings::default(),
+        );
+        let mut script = vec![
+            Weight::Stable(0.),
+            Weight::Unstable(40.),
+            Weight::Unstable(101.),
+            Weight::Stable(100.),
+            Weight::Unstable(60.),
+            Weight::Stable(0.2),
+        ]
+        .into_iter();
+        let placed = wait_for(
+            || {
+                let weight = script.next().unwrap();
+                Ok(scale
+                    .is_present_weight(&weight, 5.)
+                    .then(|| weight.get_amount()))
+            },
+            Duration::from_secs(1),
+            Duration::ZERO,
+        )?;
+        assert_eq!(placed, 100.);
+        wait_for(
+            || Ok(scale.is_clear_weight(&script.next().unwrap()).then_some(())),
+            Duration::from_secs(1),
+            Duration::ZERO,
+        )?;
+        assert!(script.next().is_none());
+        Ok(())
+    }
+    #[test]
+    fn wait_for_times_out() {
+        let result: Result<(), Error> = wait_for(
+            || Ok(None),
+            Duration::from_millis(10),
+            Duration::from_millis(1),
+        );
+        assert!(matches!(result, Err(Error::Timeout)));
+    }
+    #[test]
+    fn oversampling_buffers_group_averages() -> Result<(), Error> {
+        let config = Config {
+            buffer_length: 3,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        let mut raw = [1., 3., 5., 7., 9., 11.].into_iter();
+        for _ in 0..3 {
+            let reading = average_of_samples(|| Ok(raw.next().unwrap()), 2, Duration::ZERO)?;
+            scale.update_buffer(reading);
+        }
+        assert_eq!(scale.weight_buffer, vec![2., 6., 10.]);
+        Ok(())
+    }
+    #[test]
+    fn settled_samples_match_stable_count() -> Result<(), Error> {
+        let mut readings = [5., 1., 1.01, 0.99, 1.02, 1., 1.].into_iter();
+        let (reading, samples) = read_once_settled(
+            || Ok(readings.next().unwrap()),
+            4,
+            Duration::from_secs(1),
+            0.1,
+            Duration::ZERO,
+        )?;
+        assert_eq!(reading, 1.);
+        assert_eq!(samples, vec![1.01, 0.99, 1.02, 1.]);
+        Ok(())
+    }
+    #[test]
+    fn adaptive_settle_samples_more_when_noisy() -> Result<(), Error> {
+        let alternating = |amplitude: f64| {
+            let mut i = 0;
+            move || {
+                i += 1;
+                Ok(100. + if i % 2 == 0 { amplitude } else { -amplitude })
+            }
+        };
+        let timeout = Duration::from_secs(1);
+        let (quiet_mean, quiet_count) =
+            mean_to_precision(alternating(0.1), 0.05, timeout, Duration::ZERO)?;
+        let (noisy_mean, noisy_count) =
+            mean_to_precision(alternating(2.), 0.05, timeout, Duration::ZERO)?;
+        assert!(noisy_count > quiet_count);
+        assert!((quiet_mean - 100.).abs() < 0.1);
+        assert!((noisy_mean - 100.).abs() < 0.1);
+        Ok(())
+    }
+    #[test]
+    fn bipolar_actions_follow_magnitude() {
+        let config = Config {
+            buffer_length: 3,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(
+            config,
+            Settings {
+                bipolar: true,
+                ..Default::default()
+            },
+        );
+        assert!(settle_at(&mut scale, 0.).is_none());
+        let (action, delta) = settle_at(&mut scale, -50.).unwrap();
+        assert!(matches!(action, Action::Refilled));
+        assert_eq!(delta, 50.);
+        let (action, delta) = settle_at(&mut scale, -20.).unwrap();
+        assert!(matches!(action, Action::Served));
+        assert_eq!(delta, -30.);
+    }
+    #[test]
+    fn unipolar_actions_follow_sign() {
+        let config = Config {
+            buffer_length: 3,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        assert!(settle_at(&mut scale, 0.).is_none());
+        let (action, delta) = settle_at(&mut scale, -50.).unwrap();
+        assert!(matches!(action, Action::Served));
+        assert_eq!(delta, -50.);
+    }
+    #[test]
+    fn action_delta_rounded_but_baseline_kept() {
+        let config = Config {
+            buffer_length: 3,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(
+            config,
+            Settings {
+                action_increment: Some(0.5),
+                ..Default::default()
+            },
+        );
+        settle_at(&mut scale, 0.);
+        let (_, delta) = settle_at(&mut scale, 100.37).unwrap();
+        assert_eq!(delta, 100.5);
+        assert_eq!(scale.last_stable_weight, Some(100.37));
+    }
+    #[test]
+    fn stability_window_settles_sooner_after_step() {
+        let config = Config {
+            buffer_length: 10,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let samples_to_settle = |settings: Settings| {
+            let mut scale = unopened_scale(config.clone(), settings);
+            for _ in 0..10 {
+                scale.update_buffer(0.);
+            }
+            (1..)
+                .find(|_| {
+                    scale.update_buffer(100.);
+                    scale.is_stable()
+                })
+                .unwrap()
+        };
+        let full = samples_to_settle(Settings::default());
+        let windowed = samples_to_settle(Settings {
+            stability_window: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(full, 10);
+        assert_eq!(windowed, 3);
+    }
+    #[test]
+    fn dual_ema_settles_without_filling_buffer() {
+        let config = Config {
+            buffer_length: 10,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(
+            config,
+            Settings {
+                stability_mode: StabilityMode::DualEma {
+                    fast_alpha: 0.8,
+                    slow_alpha: 0.5,
+                },
+                ..Default::default()
+            },
+        );
+        scale.update_buffer(0.);
+        assert!(scale.is_stable());
+        scale.update_buffer(100.);
+        assert!(!scale.is_stable());
+        let samples = 1
+            + (1..)
+                .find(|_| {
+                    scale.update_buffer(100.);
+                    scale.is_stable()
+                })
+                .unwrap();
+        assert_eq!(samples, 7);
+    }
+    #[test]
+    fn tare_emits_no_action() -> Result<(), Error> {
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(3, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), [100.]);
+        for _ in 0..3 {
+            scale.get_weight()?;
+        }
+        assert!(scale.check_for_action().is_none());
+        scale.tare_averaged(3, Duration::from_secs(1))?;
+        for _ in 0..6 {
+            scale.get_weight()?;
+            assert!(scale.check_for_action().is_none());
+        }
+        assert_eq!(scale.reconcile(0., 1.).scale_delta, 0.);
+        Ok(())
+    }
+    #[test]
+    fn dual_ema_step_is_one_action() {
+        let config = Config {
+            buffer_length: 10,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(
+            config,
+            Settings {
+                stability_mode: StabilityMode::DualEma {
+                    fast_alpha: 0.8,
+                    slow_alpha: 0.5,
+                },
+                ..Default::default()
+            },
+        );
+        scale.update_buffer(0.);
+        assert!(scale.check_for_action().is_none());
+        let actions: Vec<_> = (0..20)
+            .filter_map(|_| {
+                scale.update_buffer(100.);
+                scale.check_for_action()
+            })
+            .collect();
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(actions[0].0, Action::Refilled));
+        assert!((actions[0].1 - 100.).abs() < 1.);
+    }
+    #[test]
+    fn kilogram_config_displays_in_grams() {
+        let scale = unopened_scale(
+            Config {
+                max_noise: 0.005,
+                ..Default::default()
+            },
+            Settings {
+                weight_unit: WeightUnit::Kilograms,
+                ..Default::default()
+            },
+        );
+        assert_eq!(scale.format_weight(&Weight::Stable(1.5)), "Stable: 1500 g");
+    }
+    #[test]
+    fn convert_raw_matches_live_readings() -> Result<(), Error> {
+        let raw = [1.2e-4, -3e-6, 2.5e-4];
+        let config = Config {
+            gain: 4_500_000.,
+            offset: 12.5,
+            ..scripted_config(1, 1.)
+        };
+        let scale = scripted_scale(config, Settings::default(), raw);
+        let live: Vec<f64> = (0..raw.len())
+            .map(|_| scale.get_reading())
+            .collect::<Result<_, _>>()?;
+        assert_eq!(convert_raw(&raw, 4_500_000., 12.5), live);
+        let mut in_place = raw;
+        convert_raw_in_place(&mut in_place, 4_500_000., 12.5);
+        assert_eq!(in_place.to_vec(), live);
+        Ok(())
+    }
+    #[test]
+    fn slow_decline_is_a_leak() {
+        let mut scale = unopened_scale(scripted_config(3, 1.), Settings::default());
+        let start = Instant::now();
+        let window = Duration::from_secs(60);
+        let mut leak = None;
+        for second in 0..=60 {
+            let weight = Weight::Stable(500. - 0.05 * second as f64);
+            let at = start + Duration::from_secs(second);
+            leak = scale.leak_rate(&weight, at, 0.01, window);
+            if second < 54 {
+                assert_eq!(leak, None);
+            }
+        }
+        assert!((leak.unwrap() - 0.05).abs() < 1e-9);
+        let mut served = unopened_scale(scripted_config(3, 1.), Settings::default());
+        for second in 0..=60 {
+            let weight = Weight::Stable(if second < 30 { 500. } else { 450. });
+            let at = start + Duration::from_secs(second);
+            assert_eq!(served.leak_rate(&weight, at, 0.01, window), None);
+        }
+        let mut steady = unopened_scale(scripted_config(3, 1.), Settings::default());
+        for second in 0..=60 {
+            let at = start + Duration::from_secs(second);
+            assert_eq!(
+                steady.leak_rate(&Weight::Stable(500.), at, 0.01, window),
+                None
+            );
+        }
+    }
+    #[test]
+    fn paused_scale_suppresses_readings() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(2, 1.),
+            Settings::default(),
+            [10., 10., 50., 50.],
+        );
+        scale.get_weight()?;
+        assert!(scale.get_weight()?.is_stable());
+        scale.pause();
+        assert!(scale.is_paused());
+        assert!(matches!(scale.get_weight(), Err(Error::Paused)));
+        assert_eq!(scale.buffer_snapshot(), vec![10., 10.]);
+        scale.resume();
+        assert!(scale.buffer_snapshot().is_empty());
+        assert!(matches!(scale.get_weight()?, Weight::Unstable(50.)));
+        assert!(matches!(scale.get_weight()?, Weight::Stable(50.)));
+        Ok(())
+    }
+    #[test]
+    fn calibration_age_fresh_and_overdue() -> Result<(), Error> {
+        let day = Duration::from_secs(86_400);
+        let mut scale = unopened_scale(scripted_config(1, 1.), Settings::default());
+        assert_eq!(scale.calibration_age(), None);
+        assert!(scale.needs_recalibration(day * 30));
+        scale.set_calibration(1e-4, 2e-4, 1000.)?;
+        assert!(scale.calibration_age().unwrap() < Duration::from_secs(5));
+        assert!(!scale.needs_recalibration(day * 30));
+        scale.settings.calibrated_at = Some(SystemTime::now() - day * 45);
+        assert!(scale.calibration_age().unwrap() >= day * 45);
+        assert!(scale.needs_recalibration(day * 30));
+        assert_eq!(
+            scale.calibration_report().calibrated_at,
+            scale.settings.calibrated_at
+        );
+        Ok(())
+    }
+    #[test]
+    fn reconcile_against_external_meter() {
+        let mut scale = unopened_scale(scripted_config(3, 1.), Settings::default());
+        assert!(!scale.reconcile(25., 2.).agree);
+        settle_at(&mut scale, 500.);
+        let (action, delta) = settle_at(&mut scale, 450.).unwrap();
+        assert!(matches!(action, Action::Served));
+        assert_eq!(delta, -50.);
+        assert_eq!(
+            scale.reconcile(49., 2.),
+            ReconcileResult {
+                scale_delta: 50.,
+                external: 49.,
+                agree: true
+            }
+        );
+        assert!(!scale.reconcile(40., 2.).agree);
+        scale.reset_state();
+        assert_eq!(scale.reconcile(49., 2.).scale_delta, 0.);
+    }
+    #[test]
+    fn format_weight_uses_display_precision() {
+        let mut scale = unopened_scale(scripted_config(1, 1.), Settings::default());
+        assert_eq!(scale.format_weight(&Weight::Stable(12.345)), "Stable: 12 g");
+        scale.settings.display_precision = 2;
+        assert_eq!(
+            scale.format_weight(&Weight::Unstable(12.345)),
+            "Unstable: 12.35 g"
+        );
+        assert_eq!(format!("{:.1}", Weight::Stable(-0.26)), "Stable: -0.3 g");
+    }
+    #[test]
+    fn kilogram_actions_are_reported_in_grams() -> Result<(), Error> {
+        let mut scale = scripted_scale(
+            scripted_config(3, 0.005),
+            Settings {
+                weight_unit: WeightUnit::Kilograms,
+                ..Default::default()
+            },
+            [2., 2., 2., 1.75, 1.75, 1.75],
+        );
+        let mut events = Vec::new();
+        for _ in 0..6 {
+            scale.get_weight()?;
+            events.extend(scale.check_for_action_event());
+        }
+        assert_eq!(events.len(), 1);
+        assert!(matches!(events[0].action, Action::Served));
+        assert_eq!(events[0].delta, -250.);
+        assert_eq!(events[0].unit, WeightUnit::Grams);
+        Ok(())
+    }
+    #[test]
+    fn oversized_buffer_rejected_at_connect() {
+        let connect = |settings: Settings| {
+            DisconnectedScale::new(
+                scripted_config(100_000, 1.),
+                Device::new(Model::LibraV0, "L0"),
+            )
+            .with_settings(settings)
+            .connect_with_source(ScriptedSource::new([0.]))
+        };
+        assert!(matches!(
+            connect(Settings::default()),
+            Err(Error::InvalidConfig(_))
+        ));
+        let scale = connect(Settings {
+            max_buffer_length: usize::MAX,
+            ..Default::default()
+        });
+        assert!(scale.is_ok());
+    }
+    #[test]
+    fn mislabelled_unit_rejected_at_connect() {
+        let config = Config {
+            max_noise: 5.,
+            ..Default::default()
+        };
+        let result = DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0"))
+            .with_settings(Settings {
+                weight_unit: WeightUnit::Kilograms,
+                ..Default::default()
+            })
+            .connect();
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+    }
+    #[test]
+    fn from_source_validates_config() {
+        let oversized = Config {
+            buffer_length: 10_000_000,
+            ..scripted_config(1, 1.)
+        };
+        let device = Device::new(Model::LibraV0, "L0");
+        assert!(matches!(
+            Scale::from_source(ScriptedSource::new([0.]), oversized, device.clone()),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            Scale::with_settings(scripted_config(1, 5_000.), device, Settings::default()),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+    #[test]
+    fn unopened_scale_is_not_connected() {
+        let scale = unopened_scale(Config::default(), Settings::default());
+        assert!(!scale.is_connected());
+    }
+    #[test]
+    fn partial_stats_on_half_full_buffer() {
+        let config = Config {
+            buffer_length: 6,
+            phidget_sample_period: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        scale.update_buffer(10.);
+        assert!(scale.buffer_stats_partial().is_none());
+        assert!(scale.flow_rate_partial().is_none());
+        scale.update_buffer(12.);
+        scale.update_buffer(14.);
+        assert!(scale.buffer_stats().is_none());
+        assert!(scale.flow_rate().is_none());
+        let stats = scale.buffer_stats_partial().unwrap();
+        assert_eq!(stats.mean, 12.);
+        assert_eq!(stats.std_dev, 2.);
+        assert_eq!((stats.min, stats.max, stats.samples), (10., 14., 3));
+        assert!((scale.flow_rate_partial().unwrap() - 4.).abs() < 1e-9);
+    }
+    #[test]
+    fn time_to_empty_projects_steady_depletion() {
+        let config = Config {
+            buffer_length: 5,
+            phidget_sample_period: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let settings = Settings {
+            empty_threshold: 10.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config.clone(), settings.clone());
+        for weight in [100., 98., 96., 94.] {
+            scale.update_buffer(weight);
+        }
+        assert_eq!(scale.time_to_empty(), None);
+        scale.update_buffer(92.);
+        let eta = scale.time_to_empty().unwrap();
+        assert!((eta.as_secs_f64() - 41.).abs() < 1e-6);
+        for weight in [92., 95., 98., 101., 104.] {
+            scale.update_buffer(weight);
+        }
+        assert_eq!(scale.time_to_empty(), None);
 
+        let mut bipolar = unopened_scale(
+            config,
+            Settings {
+                bipolar: true,
+                ..settings
+            },
+        );
+        for weight in [100., 98., 96., 94., 92.] {
+            bipolar.update_buffer(weight);
+        }
+        assert_eq!(bipolar.time_to_empty(), None);
+    }
+    #[test]
+    fn transient_bump_needs_confirmation() {
         let config = Config {
-            phidget_id: 716588,
-            load_cell_id: 0,
-            gain: test_weight / (weight_reading - empty_reading),
-            offset: test_weight * empty_reading / (weight_reading - empty_reading),
+            buffer_length: 3,
+            max_noise: 1.,
             ..Default::default()
         };
+        let mut scale = unopened_scale(
+            config,
+            Settings {
+                action_confirm_samples: 3,
+                ..Default::default()
+            },
+        );
+        assert!(settle_at(&mut scale, 0.).is_none());
+        assert!(settle_at(&mut scale, 50.).is_none());
+        assert!(settle_at(&mut scale, 0.).is_none());
+        assert!(settle_at(&mut scale, 0.).is_none());
 
+        assert!(settle_at(&mut scale, 50.).is_none());
+        assert!(settle_at(&mut scale, 50.).is_none());
+        let (action, delta) = settle_at(&mut scale, 50.).unwrap();
+        assert!(matches!(action, Action::Refilled));
+        assert_eq!(delta, 50.);
+        assert!(settle_at(&mut scale, 50.).is_none());
+    }
+    #[test]
+    fn prometheus_metrics_format() {
+        let config = Config {
+            buffer_length: 3,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        for w in [123., 123.5, 123.4] {
+            scale.update_buffer(w);
+        }
+        let metrics = scale.format_metrics(&Weight::Stable(123.4));
+        let label = format!("{{device=\"{}\"}}", scale.device);
+        let parsed: Vec<(&str, f64)> = metrics
+            .lines()
+            .map(|line| {
+                let (name, value) = line.rsplit_once(' ').unwrap();
+                (name, value.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (format!("scale_weight_grams{label}").as_str(), 123.4),
+                (format!("scale_stable{label}").as_str(), 1.),
+                (format!("scale_noise_grams{label}").as_str(), 0.5),
+            ]
+        );
+    }
+    #[test]
+    fn span_recalibration_keeps_offset() -> Result<(), Error> {
+        let config = Config {
+            gain: 9_000_000.,
+            offset: 25.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        let reference_reading = 0.0001232;
+        let gain = scale.apply_span(reference_reading, 1277.)?;
+        assert_eq!(scale.config.gain, gain);
+        assert_eq!(scale.config.offset, 25.);
+        let weight = reference_reading * scale.config.gain - scale.config.offset;
+        assert!((weight - 1277.).abs() < 1e-9);
+        assert!(matches!(
+            scale.apply_span(0., 1277.),
+            Err(Error::InvalidConfig(_))
+        ));
+        Ok(())
+    }
+    #[test]
+    fn calibration_rejects_swapped_readings() {
+        let mut scale = unopened_scale(Config::default(), Settings::default());
+        let before = scale.get_config();
+        let result = scale.set_calibration(0.0001232, -0.0000031, 1277.);
+        assert!(
+            matches!(result, Err(Error::InvalidConfig(message)) if message.contains("swapped"))
+        );
+        assert_eq!(scale.config.gain, before.gain);
+        assert_eq!(scale.config.offset, before.offset);
+        assert!(matches!(
+            scale.apply_span(-0.0001232, 1277.),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+    #[test]
+    fn gain_and_offset_set_independently() -> Result<(), Error> {
+        let config = Config {
+            gain: 9_000_000.,
+            offset: 25.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        scale.set_gain(10_000_000.)?;
+        assert_eq!((scale.config.gain, scale.config.offset), (10_000_000., 25.));
+        scale.set_offset(-3.);
+        assert_eq!((scale.config.gain, scale.config.offset), (10_000_000., -3.));
+        assert!(matches!(
+            scale.set_gain(-10_000_000.),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            scale.set_gain(f64::NAN),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert_eq!(scale.config.gain, 10_000_000.);
+        Ok(())
+    }
+    #[test]
+    fn calibrate_and_verify_commits() -> Result<(), Error> {
+        let readings = [[1e-4; 3], [2e-4; 3]].concat();
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(3, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), readings);
+        scale.calibrate_and_verify(1000., 1., 3, Duration::from_secs(1), |_| {})?;
+        assert!((scale.config.gain - 1e7).abs() < 1.);
+        assert_eq!(scale.verified_with, Some(1000.));
+        Ok(())
+    }
+    #[test]
+    fn calibrate_and_verify_rolls_back() {
+        let readings = [[1e-4; 3], [2e-4; 3], [2.5e-4; 3]].concat();
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(3, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), readings);
+        let result = scale.calibrate_and_verify(1000., 1., 3, Duration::from_secs(1), |_| {});
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+        assert_eq!((scale.config.gain, scale.config.offset), (1., 0.));
+        assert_eq!(scale.verified_with, None);
+    }
+    #[cfg(feature = "temperature")]
+    #[test]
+    fn calibrate_and_verify_rollback_keeps_temperature_calibration() {
+        use crate::calibration::Calibration;
+        let readings = [[1e-4; 3], [2e-4; 3], [2.5e-4; 3]].concat();
+        let config = Config {
+            phidget_sample_period: Duration::from_millis(1),
+            ..scripted_config(3, 1.)
+        };
+        let mut scale = scripted_scale(config, Settings::default(), readings);
+        let calibration = Calibration {
+            gain: 1e6,
+            offset: 0.,
+        };
+        scale.set_temp_calibration(Some(TempCalibration {
+            cold: (0., calibration),
+            hot: (40., calibration),
+        }));
+        let before = scale.temp_calibration;
+        let result = scale.calibrate_and_verify(1000., 1., 3, Duration::from_secs(1), |_| {});
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+        assert!(before.is_some());
+        assert_eq!(scale.temp_calibration, before);
+    }
+    #[test]
+    fn counts_per_gram_calibration() -> Result<(), Error> {
         let mut scale =
-            DisconnectedScale::new(config, Device::new(Model::LibraV0, "L0")).connect()?;
-        scale.set_calibration(empty_reading, weight_reading, test_weight);
-        Ok(scale)
+            DisconnectedScale::new(scripted_config(3, 1.), Device::new(Model::LibraV0, "L0"))
+                .connect_with_source(
+                    ScriptedSource::new([8_000., 50_000.]).with_plausible_gain(1e-4..=1.),
+                )?;
+        assert!(matches!(
+            scale.set_counts_calibration(-420., 8_000.),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            scale.set_counts_calibration(1e-6, 8_000.),
+            Err(Error::InvalidConfig(_))
+        ));
+        scale.set_counts_calibration(420., 8_000.)?;
+        assert!((scale.get_weight()?.get_amount()).abs() < 1e-9);
+        assert!((scale.get_weight()?.get_amount() - 100.).abs() < 1e-9);
+        assert!((scale.counts_per_gram() - 420.).abs() < 1e-9);
+        assert!((scale.zero_count() - 8_000.).abs() < 1e-9);
+        assert!(matches!(
+            scale.set_counts_calibration(0., 8_000.),
+            Err(Error::InvalidConfig(_))
+        ));
+        Ok(())
     }
     #[test]
-    fn weigh_once_settled() -> Result<(), Error> {
-        let scale = make_scale()?;
-        let weight = scale.weigh_once_settled(3, Duration::from_secs(10), 0.1)?;
-        println!("DEBUG: {weight}");
+    fn calibration_rejects_equal_readings() {
+        let mut scale = unopened_scale(Config::default(), Settings::default());
+        let result = scale.set_calibration(0.0001232, 0.0001232, 1277.);
+        assert!(matches!(result, Err(Error::InvalidConfig(message)) if message.contains("equal")));
+        assert!(scale.set_calibration(-0.0000031, 0.0001232, 1277.).is_ok());
+        assert!(matches!(
+            scale.set_calibration(0., 5., 1277.),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+    #[test]
+    fn buffer_follows_changed_length() {
+        let mut scale = unopened_scale(scripted_config(5, 1.), Settings::default());
+        for weight in [40., 30., 20., 10., 10.] {
+            scale.update_buffer(weight);
+        }
+        assert!(!scale.is_stable());
+        scale.config.buffer_length = 3;
+        scale.update_buffer(10.);
+        assert_eq!(scale.weight_buffer, vec![10., 10., 10.]);
+        assert!(scale.is_stable());
+        assert!(scale.buffer_stats().is_some());
+        scale.config.buffer_length = 4;
+        assert!(!scale.is_stable());
+        scale.update_buffer(10.);
+        assert!(scale.is_stable());
+        scale.config.buffer_length = 0;
+        scale.update_buffer(7.);
+        assert_eq!(scale.weight_buffer, vec![7.]);
+    }
+    #[test]
+    fn reset_state_clears_buffer_and_baseline() {
+        let config = Config {
+            buffer_length: 3,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        settle_at(&mut scale, 10.);
+        assert!(scale.last_stable_weight.is_some());
+        scale.reset_state();
+        assert!(scale.weight_buffer.is_empty());
+        assert!(scale.last_stable_weight.is_none());
+        assert!(scale.pending_action.is_none());
+    }
+    #[test]
+    fn instant_weight_leaves_buffer_untouched() {
+        let config = Config {
+            buffer_length: 3,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        scale.update_buffer(1.);
+        scale.update_buffer(2.);
+        let _ = scale.get_instant_weight();
+        assert_eq!(scale.weight_buffer, vec![1., 2.]);
+        assert!(scale.last_stable_weight.is_none());
+    }
+    #[test]
+    fn action_logging_can_be_disabled() {
+        capture::init();
+        let config = Config {
+            buffer_length: 3,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut quiet = unopened_scale(
+            config.clone(),
+            Settings {
+                log_actions: false,
+                ..Default::default()
+            },
+        );
+        quiet.device = Device::new(Model::LibraV0, "quiet");
+        settle_at(&mut quiet, 0.);
+        assert!(settle_at(&mut quiet, 100.).is_some());
+        assert!(capture::for_device(&quiet.device.to_string()).is_empty());
+
+        let mut loud = unopened_scale(
+            config,
+            Settings {
+                action_log_level: log::Level::Debug,
+                ..Default::default()
+            },
+        );
+        loud.device = Device::new(Model::LibraV0, "loud");
+        settle_at(&mut loud, 0.);
+        assert!(settle_at(&mut loud, 100.).is_some());
+        let records = capture::for_device(&loud.device.to_string());
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, log::Level::Debug);
+        assert!(records[0].message.contains("Delta: 100"));
+    }
+    #[test]
+    fn small_actions_returned_but_not_logged() -> Result<(), Error> {
+        capture::init();
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings {
+                log_min_delta: 10.,
+                ..Default::default()
+            },
+            [0., 0., 0., 5., 5., 5., 50., 50., 50.],
+        );
+        scale.device = Device::new(Model::LibraV0, "min-delta");
+        let mut actions = Vec::new();
+        for _ in 0..9 {
+            scale.get_weight()?;
+            actions.extend(scale.check_for_action());
+        }
+        assert_eq!(actions.len(), 2);
+        let records = capture::for_device(&scale.device.to_string());
+        assert_eq!(records.len(), 1);
+        assert!(records[0].message.contains("Delta: 45"));
+        Ok(())
+    }
+    #[test]
+    fn capacity_warning_fires_once_in_band() -> Result<(), Error> {
+        capture::init();
+        let mut scale = scripted_scale(
+            scripted_config(3, 1.),
+            Settings {
+                max_rated_weight: Some(1000.),
+                warn_capacity_fraction: 0.9,
+                ..Default::default()
+            },
+            [500., 850., 920., 950., 930., 400., 910.],
+        );
+        scale.device = Device::new(Model::LibraV0, "capacity");
+        let device = scale.device.to_string();
+        scale.get_weight()?;
+        scale.get_weight()?;
+        assert!(capture::for_device(&device).is_empty());
+        scale.get_weight()?;
+        scale.get_weight()?;
+        scale.get_weight()?;
+        let records = capture::for_device(&device);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, log::Level::Warn);
+        scale.get_weight()?;
+        scale.get_weight()?;
+        assert_eq!(capture::for_device(&device).len(), 1);
         Ok(())
     }
+    #[test]
+    fn time_to_stable_for_decaying_noise() {
+        let config = Config {
+            buffer_length: 10,
+            max_noise: 1.,
+            phidget_sample_period: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        for i in 0..10 {
+            let amplitude = 10. * 0.8_f64.powi(i);
+            scale.update_buffer(if i % 2 == 0 { amplitude } else { -amplitude });
+        }
+        let estimate = scale.estimate_time_to_stable().unwrap();
+        assert!(estimate >= Duration::from_millis(500));
+        assert!(estimate < Duration::from_secs(5));
+
+        for _ in 0..10 {
+            scale.update_buffer(0.);
+        }
+        assert!(scale.estimate_time_to_stable().is_none());
+    }
+    #[test]
+    fn time_to_stable_unpredictable_for_growing_noise() {
+        let config = Config {
+            buffer_length: 10,
+            max_noise: 1.,
+            ..Default::default()
+        };
+        let mut scale = unopened_scale(config, Settings::default());
+        for i in 0..10 {
+            let amplitude = 1.5_f64.powi(i);
+            scale.update_buffer(if i % 2 == 0 { amplitude } else { -amplitude });
+        }
+        assert!(scale.estimate_time_to_stable().is_none());
+    }
+    #[test]
+    fn weight_to_milligrams() {
+        assert_eq!(
+            Weight::Stable(12.3456).to_milligrams(WeightUnit::Grams),
+            Milligrams(12346)
+        );
+        assert_eq!(
+            Weight::Unstable(-0.5).to_milligrams(WeightUnit::Grams),
+            Milligrams(-500)
+        );
+        assert_eq!(
+            Weight::Stable(1.5).to_milligrams(WeightUnit::Kilograms),
+            Milligrams(1_500_000)
+        );
+    }
+    #[test]
+    fn negative_weights_display_signed() {
+        assert_eq!(Weight::Stable(-12.7).to_string(), "Stable: -13 g");
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhidgetInfo {
+    pub serial: i32,
+    pub channel: i32,
+    pub attached: bool,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconcileResult {
+    /// Amount the scale saw served, as a positive weight.
+    pub scale_delta: f64,
+    pub external: f64,
+    pub agree: bool,
+}
+/// Samples buffered so far against those stability needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotReady {
+    pub buffered: usize,
+    pub needed: usize,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossDir {
+    Rising,
+    Falling,
+}
+pub struct AlarmSpec {
+    pub level: f64,
+    pub direction: CrossDir,
+    /// Called with the stable weight that crossed `level`.
+    pub cb: Box<dyn FnMut(f64) + Send>,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadPrompt {
+    Place,
+    Remove,
+}
+#[derive(Debug, Clone)]
+pub struct ActionEvent {
+    pub device: Device,
+    pub action: Action,
+    pub delta: f64,
+    /// Unit of `delta`; always grams, whatever the scale's configured unit.
+    pub unit: WeightUnit,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Rising,
+    Falling,
+    Flat,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettleState {
+    pub stable: bool,
+    pub direction: Direction,
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BestEffortReading {
+    pub value: f64,
+    pub settled: bool,
+    pub samples_taken: usize,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferStats {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub samples: usize,
+}
+impl BufferStats {
+    fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.len() < 2 {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.);
+        Some(Self {
+            mean,
+            std_dev: variance.sqrt(),
+            min: samples.iter().fold(f64::INFINITY, |a, &b| a.min(b)),
+            max: samples.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b)),
+            samples: samples.len(),
+        })
+    }
+}
+#[derive(Debug)]
+pub struct TimedWeight {
+    pub weight: Weight,
+    /// Monotonic, for aligning with other local sensor streams.
+    pub at: Instant,
+    /// Wall clock, for telemetry.
+    pub timestamp: SystemTime,
+}
+impl TimedWeight {
+    pub fn epoch_millis(&self) -> u128 {
+        self.timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|since| since.as_millis())
+            .unwrap_or(0)
+    }
 }
 #[derive(Debug)]
 pub enum Weight {
@@ -250,12 +4346,27 @@ impl Weight {
             Weight::Unstable(value) => *value,
         }
     }
+    pub fn is_stable(&self) -> bool {
+        matches!(self, Weight::Stable(_))
+    }
+    pub fn is_unstable(&self) -> bool {
+        matches!(self, Weight::Unstable(_))
+    }
+    /// `unit` is the one the weight was read in, the scale's `Settings::weight_unit`.
+    pub fn to_milligrams(&self, unit: WeightUnit) -> Milligrams {
+        Milligrams::from_grams(unit.to_grams(self.get_amount()))
+    }
 }
+/// Whole grams by default; a precision such as `{:.2}` prints that many decimals.
 impl std::fmt::Display for Weight {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Weight::Stable(w) => write!(f, "Stable: {} g", w.trunc() as usize),
-            Weight::Unstable(w) => write!(f, "Unstable: {} g", w.trunc() as usize),
+        let (label, w) = match self {
+            Weight::Stable(w) => ("Stable", w),
+            Weight::Unstable(w) => ("Unstable", w),
+        };
+        match f.precision() {
+            Some(precision) => write!(f, "{label}: {w:.precision$} g"),
+            None => write!(f, "{label}: {w:.0} g"),
         }
     }
 }
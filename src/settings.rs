@@ -0,0 +1,158 @@
+use crate::error::Error;
+use crate::unit::WeightUnit;
+use log::Level;
+use menu::libra::Config;
+use serde::{Serialize, Serializer};
+use std::time::{Duration, SystemTime};
+
+const PLAUSIBLE_NOISE_GRAMS: std::ops::RangeInclusive<f64> = 0.0..=1000.;
+pub const MAX_BUFFER_LENGTH: usize = 10_000;
+
+/// Scale behaviour that isn't covered by the `menu` libra `Config`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Settings {
+    /// Tension/compression cell: actions follow the magnitude of the reading, not its sign.
+    pub bipolar: bool,
+    pub log_actions: bool,
+    #[serde(serialize_with = "serialize_display")]
+    pub action_log_level: Level,
+    /// Actions smaller than this are still returned but not logged.
+    pub log_min_delta: f64,
+    /// Raw readings averaged into each buffered sample.
+    pub oversample: usize,
+    /// Reported action deltas are rounded to this increment; the baseline keeps full precision.
+    pub action_increment: Option<f64>,
+    /// Only the latest samples (capped at `buffer_length`) need to be within `max_noise`.
+    pub stability_window: Option<usize>,
+    pub stability_mode: StabilityMode,
+    /// Unit the `Config`'s gain, offset and max_noise are authored in.
+    pub weight_unit: WeightUnit,
+    /// Consecutive stable checks a new level must hold before an action is reported.
+    pub action_confirm_samples: usize,
+    pub max_rated_weight: Option<f64>,
+    /// Fraction of `max_rated_weight` above which `get_weight` warns, once per excursion.
+    pub warn_capacity_fraction: f64,
+    /// Network Phidget server to open the load cell through; local USB when `None`.
+    pub phidget_server: Option<ServerAddr>,
+    /// `get_instant_weight` reuses a raw reading younger than this instead of hitting the driver.
+    pub read_cache_ttl: Option<Duration>,
+    /// Report `Action::Starting` with the initial weight on the first stable reading.
+    pub emit_starting: bool,
+    pub settle_mode: SettleMode,
+    /// Weight at or below which the scale counts as empty for `time_to_empty`.
+    pub empty_threshold: f64,
+    /// Configs asking for a longer weight buffer are rejected rather than allocated.
+    pub max_buffer_length: usize,
+    /// Step `display_value` quantizes to.
+    pub display_increment: f64,
+    /// When gain was last set, for tracking calibration age. Updated by the calibration
+    /// methods; restore it from a saved `CalibrationReport::calibrated_at` on startup.
+    pub calibrated_at: Option<SystemTime>,
+    /// Decimal places `format_weight` prints.
+    pub display_precision: usize,
+    /// Don't buffer a sample whose raw reading is bit-identical to the previous one, as
+    /// happens when polling faster than the phidget's data interval.
+    pub skip_duplicate_reads: bool,
+    /// After the weight has been stable this long, sample at `idle_period` until it moves.
+    pub idle_after: Option<Duration>,
+    pub idle_period: Duration,
+    /// Blend a new tare's zero in over this long instead of stepping to it.
+    pub tare_ramp: Option<Duration>,
+    /// Settle and capture paths read the median of three raw reads to drop single glitches.
+    pub deglitch_raw: bool,
+    /// Hub channel of a temperature sensor to attach on connect.
+    #[cfg(feature = "temperature")]
+    pub temperature_channel: Option<i32>,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            bipolar: false,
+            log_actions: true,
+            action_log_level: Level::Info,
+            log_min_delta: 0.,
+            oversample: 1,
+            action_increment: None,
+            stability_window: None,
+            stability_mode: StabilityMode::PeakToPeak,
+            weight_unit: WeightUnit::Grams,
+            action_confirm_samples: 1,
+            max_rated_weight: None,
+            warn_capacity_fraction: 0.9,
+            phidget_server: None,
+            read_cache_ttl: None,
+            emit_starting: false,
+            settle_mode: SettleMode::ResetOnOutlier,
+            empty_threshold: 0.,
+            max_buffer_length: MAX_BUFFER_LENGTH,
+            display_increment: 1.,
+            display_precision: 0,
+            calibrated_at: None,
+            skip_duplicate_reads: false,
+            idle_after: None,
+            idle_period: Duration::from_secs(1),
+            tare_ramp: None,
+            deglitch_raw: false,
+            #[cfg(feature = "temperature")]
+            temperature_channel: None,
+        }
+    }
+}
+/// How the weight buffer decides the reading is stable.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum StabilityMode {
+    /// The buffered window's peak-to-peak is within `max_noise`.
+    PeakToPeak,
+    /// A fast and a slow moving average agree to within `max_noise`; needs no buffer fill.
+    DualEma { fast_alpha: f64, slow_alpha: f64 },
+}
+/// How `raw_read_once_settled` decides the signal has settled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SettleMode {
+    /// Every sample must stay within the noise band of a seed reading; any outlier re-seeds.
+    ResetOnOutlier,
+    /// The peak-to-peak of the last `stable_samples` readings must be within the noise band.
+    SlidingWindow,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ServerAddr {
+    pub name: String,
+    pub address: String,
+    pub port: i32,
+    #[serde(skip_serializing)]
+    pub password: String,
+}
+impl ServerAddr {
+    pub fn new(address: &str, port: i32) -> Self {
+        Self {
+            name: address.into(),
+            address: address.into(),
+            port,
+            password: String::new(),
+        }
+    }
+}
+impl Settings {
+    pub fn validate(&self, config: &Config) -> Result<(), Error> {
+        let noise_grams = self.weight_unit.to_grams(config.max_noise);
+        if !PLAUSIBLE_NOISE_GRAMS.contains(&noise_grams) {
+            return Err(Error::InvalidConfig(format!(
+                "max_noise of {} {} is implausible; is the config authored in {}?",
+                config.max_noise, self.weight_unit, self.weight_unit
+            )));
+        }
+        if config.buffer_length > self.max_buffer_length {
+            return Err(Error::InvalidConfig(format!(
+                "buffer_length of {} exceeds the maximum of {}",
+                config.buffer_length, self.max_buffer_length
+            )));
+        }
+        Ok(())
+    }
+}
+fn serialize_display<T: std::fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
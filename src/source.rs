@@ -0,0 +1,152 @@
+use crate::error::Error;
+use log::warn;
+use menu::libra::Config;
+use phidget::{Phidget, devices::VoltageRatioInput};
+#[cfg(test)]
+use std::cell::Cell;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+pub trait RawSource {
+    fn read(&self) -> Result<f64, Error>;
+    /// Applies hardware-facing changes between two configs; sources without hardware
+    /// settings can ignore it.
+    fn reconfigure(&mut self, _old: &Config, _new: &Config) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Sources that can't change their rate report an unbounded range and ignore
+    /// [`RawSource::set_sample_period`].
+    fn min_sample_period(&self) -> Result<Duration, Error> {
+        Ok(Duration::ZERO)
+    }
+    fn max_sample_period(&self) -> Result<Duration, Error> {
+        Ok(Duration::MAX)
+    }
+    fn set_sample_period(&mut self, _period: Duration) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Gains, in grams per raw unit, that a real load cell on this source could produce.
+    /// The default suits a bridge read as a voltage ratio; sources reporting ADC counts
+    /// (an HX711 sits around 1/420 g per count) override it.
+    fn plausible_gain(&self) -> RangeInclusive<f64> {
+        1e3..=1e11
+    }
+}
+impl RawSource for VoltageRatioInput {
+    fn read(&self) -> Result<f64, Error> {
+        self.voltage_ratio().map_err(Error::Phidget)
+    }
+    fn reconfigure(&mut self, old: &Config, new: &Config) -> Result<(), Error> {
+        if old.phidget_id != new.phidget_id || old.load_cell_id != new.load_cell_id {
+            self.close()?;
+            if let Err(e) = open_as(self, new) {
+                let _ = self.close();
+                if let Err(restore) = open_as(self, old) {
+                    warn!(
+                        "Reopening phidget {} channel {} failed: {restore}",
+                        old.phidget_id, old.load_cell_id
+                    );
+                }
+                return Err(e);
+            }
+        } else if old.phidget_sample_period != new.phidget_sample_period {
+            self.set_data_interval(new.phidget_sample_period)?;
+        }
+        Ok(())
+    }
+    fn min_sample_period(&self) -> Result<Duration, Error> {
+        Ok(self.min_data_interval()?)
+    }
+    fn max_sample_period(&self) -> Result<Duration, Error> {
+        Ok(self.max_data_interval()?)
+    }
+    fn set_sample_period(&mut self, period: Duration) -> Result<(), Error> {
+        Ok(self.set_data_interval(period)?)
+    }
+}
+fn open_as(vin: &mut VoltageRatioInput, config: &Config) -> Result<(), Error> {
+    vin.set_channel(config.load_cell_id)?;
+    vin.set_serial_number(config.phidget_id)?;
+    vin.open_wait(Duration::from_secs(5))?;
+    vin.set_data_interval(config.phidget_sample_period)?;
+    Ok(())
+}
+/// Replays a script of raw readings, repeating the last one (or, when cycling, the whole
+/// script) once it runs out.
+#[cfg(test)]
+pub(crate) struct ScriptedSource {
+    readings: Vec<f64>,
+    next: Cell<usize>,
+    cycle: bool,
+    plausible_gain: Option<RangeInclusive<f64>>,
+    sample_periods: (Duration, Duration),
+    sample_period: Option<Duration>,
+}
+#[cfg(test)]
+impl ScriptedSource {
+    pub(crate) fn new(readings: impl IntoIterator<Item = f64>) -> Self {
+        Self {
+            readings: readings.into_iter().collect(),
+            next: Cell::new(0),
+            cycle: false,
+            plausible_gain: None,
+            sample_periods: (Duration::ZERO, Duration::MAX),
+            sample_period: None,
+        }
+    }
+    pub(crate) fn cycling(readings: impl IntoIterator<Item = f64>) -> Self {
+        Self {
+            cycle: true,
+            ..Self::new(readings)
+        }
+    }
+    /// Stands in for a source with different raw units, such as ADC counts.
+    pub(crate) fn with_plausible_gain(self, range: RangeInclusive<f64>) -> Self {
+        Self {
+            plausible_gain: Some(range),
+            ..self
+        }
+    }
+    pub(crate) fn with_sample_periods(self, min: Duration, max: Duration) -> Self {
+        Self {
+            sample_periods: (min, max),
+            ..self
+        }
+    }
+    /// How many readings have been taken so far.
+    pub(crate) fn reads(&self) -> usize {
+        self.next.get()
+    }
+    /// The period last passed to [`RawSource::set_sample_period`].
+    pub(crate) fn sample_period(&self) -> Option<Duration> {
+        self.sample_period
+    }
+}
+#[cfg(test)]
+impl RawSource for ScriptedSource {
+    fn read(&self) -> Result<f64, Error> {
+        if self.readings.is_empty() {
+            return Err(Error::Timeout);
+        }
+        let next = self.next.get();
+        self.next.set(next + 1);
+        if self.cycle {
+            Ok(self.readings[next % self.readings.len()])
+        } else {
+            Ok(self.readings[next.min(self.readings.len() - 1)])
+        }
+    }
+    fn min_sample_period(&self) -> Result<Duration, Error> {
+        Ok(self.sample_periods.0)
+    }
+    fn max_sample_period(&self) -> Result<Duration, Error> {
+        Ok(self.sample_periods.1)
+    }
+    fn set_sample_period(&mut self, period: Duration) -> Result<(), Error> {
+        self.sample_period = Some(period);
+        Ok(())
+    }
+    fn plausible_gain(&self) -> RangeInclusive<f64> {
+        self.plausible_gain.clone().unwrap_or(1e3..=1e11)
+    }
+}
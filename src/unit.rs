@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WeightUnit {
+    #[default]
+    Grams,
+    Kilograms,
+    Pounds,
+    Ounces,
+}
+impl WeightUnit {
+    pub fn grams_per_unit(&self) -> f64 {
+        match self {
+            WeightUnit::Grams => 1.,
+            WeightUnit::Kilograms => 1000.,
+            WeightUnit::Pounds => 453.59237,
+            WeightUnit::Ounces => 28.349523125,
+        }
+    }
+    pub fn to_grams(&self, value: f64) -> f64 {
+        value * self.grams_per_unit()
+    }
+    pub fn convert(&self, value: f64, to: WeightUnit) -> f64 {
+        self.to_grams(value) / to.grams_per_unit()
+    }
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            WeightUnit::Grams => "g",
+            WeightUnit::Kilograms => "kg",
+            WeightUnit::Pounds => "lb",
+            WeightUnit::Ounces => "oz",
+        }
+    }
+}
+impl std::fmt::Display for WeightUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct Milligrams(pub i64);
+impl Milligrams {
+    pub fn from_grams(grams: f64) -> Self {
+        Self((grams * 1000.).round() as i64)
+    }
+    pub fn as_grams(&self) -> f64 {
+        self.0 as f64 / 1000.
+    }
+}
+impl Add for Milligrams {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0)
+    }
+}
+impl Sub for Milligrams {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0)
+    }
+}
+impl Neg for Milligrams {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(-self.0)
+    }
+}
+impl AddAssign for Milligrams {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+impl SubAssign for Milligrams {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+impl Sum for Milligrams {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+impl std::fmt::Display for Milligrams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} mg", self.0)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn converts_between_units() {
+        assert_eq!(WeightUnit::Kilograms.convert(1.5, WeightUnit::Grams), 1500.);
+        assert_eq!(WeightUnit::Grams.convert(1500., WeightUnit::Kilograms), 1.5);
+        assert!((WeightUnit::Pounds.convert(1., WeightUnit::Ounces) - 16.).abs() < 1e-9);
+    }
+    #[test]
+    fn milligram_sums_are_exact() {
+        let serving = 0.1;
+        let float_total: f64 = std::iter::repeat_n(serving, 1000).sum();
+        let integer_total: Milligrams =
+            std::iter::repeat_n(Milligrams::from_grams(serving), 1000).sum();
+        assert_ne!(float_total, 100.);
+        assert_eq!(integer_total, Milligrams(100_000));
+        assert_eq!(integer_total.as_grams(), 100.);
+    }
+    #[test]
+    fn milligram_arithmetic() {
+        let mut total = Milligrams(500);
+        total += Milligrams(250);
+        total -= Milligrams(100);
+        assert_eq!(total, Milligrams(650));
+        assert_eq!(-(total - Milligrams(1000)), Milligrams(350));
+    }
+}